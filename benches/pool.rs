@@ -0,0 +1,154 @@
+//! Throughput of [Pool] against the single queue design it replaced,
+//! where every worker took each job from one shared lock.
+//!
+//! Run with `cargo bench --bench pool`.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rns::worker_pool::Pool;
+
+/// Tiny jobs sent from outside the pool.
+const FLAT_JOBS: usize = 200_000;
+/// Jobs sent from outside, each sending [NESTED_FANOUT] tiny jobs from inside.
+const NESTED_JOBS: usize = 2_000;
+const NESTED_FANOUT: usize = 100;
+
+/// The pools under comparison.
+trait Execute: Send + Sync + 'static {
+    fn create(n_workers: usize) -> Self;
+    fn execute<F: FnOnce() + Send + 'static>(&self, callable: F);
+}
+
+impl Execute for Pool {
+    fn create(n_workers: usize) -> Self {
+        Pool::new(n_workers)
+    }
+
+    fn execute<F: FnOnce() + Send + 'static>(&self, callable: F) {
+        Pool::execute(self, callable);
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// The previous design: a channel whose receiver all workers share behind
+/// one lock. The per job logging it did is left out, it would dominate.
+struct SharedQueue {
+    tx: Option<mpsc::Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>
+}
+
+impl Execute for SharedQueue {
+    fn create(n_workers: usize) -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        let workers = (0..n_workers).map(|_| {
+            let rx = rx.clone();
+            thread::spawn(move || loop {
+                let job = rx.lock().unwrap().recv();
+                match job {
+                    Ok(callable) => callable(),
+                    Err(_) => break
+                }
+            })
+        }).collect();
+
+        SharedQueue { tx: Some(tx), workers }
+    }
+
+    fn execute<F: FnOnce() + Send + 'static>(&self, callable: F) {
+        self.tx.as_ref().unwrap().send(Box::new(callable)).unwrap();
+    }
+}
+
+impl Drop for SharedQueue {
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        for worker in self.workers.drain(..) {
+            worker.join().unwrap();
+        }
+    }
+}
+
+/// Runs [FLAT_JOBS] tiny jobs sent from the main thread, returns jobs per second.
+fn flat<P: Execute>(n_workers: usize) -> f64 {
+    let pool = Arc::new(P::create(n_workers));
+    let done = Arc::new(AtomicUsize::new(0));
+
+    let started = Instant::now();
+    for _ in 0..FLAT_JOBS {
+        let done = done.clone();
+        pool.execute(move || {
+            done.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    wait(&done, FLAT_JOBS);
+    let elapsed = started.elapsed();
+
+    finish(pool);
+    FLAT_JOBS as f64 / elapsed.as_secs_f64()
+}
+
+/// Runs [NESTED_JOBS] jobs that each send [NESTED_FANOUT] tiny jobs, returns jobs per second.
+fn nested<P: Execute>(n_workers: usize) -> f64 {
+    let pool = Arc::new(P::create(n_workers));
+    let done = Arc::new(AtomicUsize::new(0));
+    let total = NESTED_JOBS * (NESTED_FANOUT + 1);
+
+    let started = Instant::now();
+    for _ in 0..NESTED_JOBS {
+        let done = done.clone();
+        let inner = pool.clone();
+        pool.execute(move || {
+            for _ in 0..NESTED_FANOUT {
+                let done = done.clone();
+                inner.execute(move || {
+                    done.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+            done.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+    wait(&done, total);
+    let elapsed = started.elapsed();
+
+    finish(pool);
+    total as f64 / elapsed.as_secs_f64()
+}
+
+/// Waits until [total] jobs are done.
+fn wait(done: &AtomicUsize, total: usize) {
+    while done.load(Ordering::Relaxed) < total {
+        thread::sleep(Duration::from_micros(100));
+    }
+}
+
+/// Drops the pool on this thread, once no job holds a reference anymore.
+fn finish<P: Execute>(pool: Arc<P>) {
+    while Arc::strong_count(&pool) > 1 {
+        thread::sleep(Duration::from_micros(100));
+    }
+    drop(pool);
+}
+
+/// Best of a few runs, to smooth out scheduling noise.
+fn best(run: impl Fn() -> f64) -> f64 {
+    (0..5).map(|_| run()).fold(0.0, f64::max)
+}
+
+fn main() {
+    println!("{:<8} {:>8} {:>16} {:>16} {:>8}", "jobs", "workers", "shared queue/s", "work stealing/s", "gain");
+    for n_workers in [1, 8, 64] {
+        let before = best(|| flat::<SharedQueue>(n_workers));
+        let after = best(|| flat::<Pool>(n_workers));
+        println!("{:<8} {:>8} {:>16.0} {:>16.0} {:>7.2}x", "flat", n_workers, before, after, after / before);
+    }
+    for n_workers in [1, 8, 64] {
+        let before = best(|| nested::<SharedQueue>(n_workers));
+        let after = best(|| nested::<Pool>(n_workers));
+        println!("{:<8} {:>8} {:>16.0} {:>16.0} {:>7.2}x", "nested", n_workers, before, after, after / before);
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun",
+    "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"
+];
+
+/// A point in time broken down into UTC calendar fields.
+/// Only covers what HTTP and logs need, so times before 1970 are clamped to the epoch.
+#[derive(Debug, PartialEq)]
+pub struct DateTime {
+    year: i64,
+    month: usize,
+    day: usize,
+    hour: u64,
+    minute: u64,
+    second: u64,
+    weekday: usize
+}
+
+impl DateTime {
+    pub fn from_system_time(time: SystemTime) -> DateTime {
+        let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        DateTime::from_unix_secs(secs)
+    }
+
+    pub fn now() -> DateTime {
+        DateTime::from_system_time(SystemTime::now())
+    }
+
+    /// Converts seconds since the epoch with the days-to-civil algorithm
+    /// by Howard Hinnant.
+    pub fn from_unix_secs(secs: u64) -> DateTime {
+        let days = (secs / 86_400) as i64;
+        let secs_of_day = secs % 86_400;
+
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        DateTime {
+            year,
+            month: month as usize,
+            day: day as usize,
+            hour: secs_of_day / 3_600,
+            minute: secs_of_day % 3_600 / 60,
+            second: secs_of_day % 60,
+            // 1970-01-01 was a Thursday
+            weekday: (days % 7) as usize
+        }
+    }
+
+    /// Formats as IMF-fixdate used by Date, Expires and similar headers,
+    /// e.g. "Sun, 06 Nov 1994 08:49:37 GMT".
+    pub fn to_http_str(&self) -> String {
+        format!(
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            WEEKDAYS[self.weekday], self.day, MONTHS[self.month - 1], self.year,
+            self.hour, self.minute, self.second
+        )
+    }
+
+    /// Formats as the timestamp of the Common Log Format,
+    /// e.g. "06/Nov/1994:08:49:37 +0000".
+    pub fn to_clf_str(&self) -> String {
+        format!(
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            self.day, MONTHS[self.month - 1], self.year,
+            self.hour, self.minute, self.second
+        )
+    }
+
+    /// Formats as an RFC 3339 UTC timestamp, e.g. "1994-11-06T08:49:37Z".
+    pub fn to_rfc3339_str(&self) -> String {
+        format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            self.year, self.month, self.day,
+            self.hour, self.minute, self.second
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,38 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::date::DateTime;
+
+#[test]
+/// Test [DateTime] to_http_str method with known dates.
+fn http_date() {
+    let cases = [
+        (0, "Thu, 01 Jan 1970 00:00:00 GMT"),
+        (784_111_777, "Sun, 06 Nov 1994 08:49:37 GMT"),
+        (951_782_400, "Tue, 29 Feb 2000 00:00:00 GMT"),
+        (1_709_164_799, "Wed, 28 Feb 2024 23:59:59 GMT"),
+        (4_102_444_800, "Fri, 01 Jan 2100 00:00:00 GMT")
+    ];
+
+    for (secs, expected) in cases {
+        let date = DateTime::from_unix_secs(secs).to_http_str();
+        assert!(date == expected, "{secs} formatted as {date}");
+    }
+}
+
+#[test]
+/// Test [DateTime] from_system_time method, including times before the epoch.
+fn from_system_time() {
+    let time = UNIX_EPOCH + Duration::from_secs(784_111_777);
+    assert!(DateTime::from_system_time(time) == DateTime::from_unix_secs(784_111_777));
+
+    let time = UNIX_EPOCH - Duration::from_secs(10);
+    assert!(DateTime::from_system_time(time) == DateTime::from_unix_secs(0));
+}
+
+#[test]
+/// Test [DateTime] log timestamp formats.
+fn log_formats() {
+    let date = DateTime::from_unix_secs(784_111_777);
+    assert!(date.to_clf_str() == "06/Nov/1994:08:49:37 +0000");
+    assert!(date.to_rfc3339_str() == "1994-11-06T08:49:37Z");
+}
@@ -0,0 +1,478 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Write};
+
+/// Nesting depth at which [Value::parse] gives up, so a hostile
+/// body of "[[[[..." can't overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value. Objects keep keys sorted, so serialization is deterministic.
+/// Numbers are stored as [f64], as in JavaScript.
+/// 
+/// # Example
+/// ```
+/// use rns::json::Value;
+/// 
+/// let value = Value::parse(r#"{"name": "Neko", "lives": 9, "tags": ["cat"]}"#).unwrap();
+/// 
+/// assert!(value.get("name").and_then(Value::as_str) == Some("Neko"));
+/// assert!(value.get("lives").and_then(Value::as_i64) == Some(9));
+/// assert!(value.to_string() == r#"{"lives":9,"name":"Neko","tags":["cat"]}"#);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(BTreeMap<String, Value>)
+}
+
+/// Describes why [Value::parse] failed and at which byte offset.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    position: usize,
+    message: &'static str
+}
+
+impl ParseError {
+    pub const fn get_position(&self) -> usize {
+        self.position
+    }
+
+    pub const fn get_message(&self) -> &'static str {
+        self.message
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.position)
+    }
+}
+
+impl Value {
+    /// Strictly parses a complete JSON text as defined by RFC 8259:
+    /// no trailing commas, comments, single quotes, leading zeros,
+    /// unescaped control characters, lone surrogates or duplicate keys.
+    pub fn parse(text: &str) -> Result<Value, ParseError> {
+        let mut parser = Parser { bytes: text.as_bytes(), position: 0 };
+
+        parser.skip_whitespace();
+        let value = parser.parse_value(0)?;
+        parser.skip_whitespace();
+
+        if parser.position != parser.bytes.len() {
+            return Result::Err(parser.error("trailing characters"))
+        }
+
+        Result::Ok(value)
+    }
+
+    /// Returns the value of [key] if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object().and_then(|object| object.get(key))
+    }
+
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub const fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None
+        }
+    }
+
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            _ => None
+        }
+    }
+
+    /// Returns the number if it is integral and fits into [i64] without loss.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => Some(*n as i64),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None
+        }
+    }
+
+    pub const fn as_array(&self) -> Option<&Vec<Value>> {
+        match self {
+            Value::Array(a) => Some(a),
+            _ => None
+        }
+    }
+
+    pub const fn as_object(&self) -> Option<&BTreeMap<String, Value>> {
+        match self {
+            Value::Object(o) => Some(o),
+            _ => None
+        }
+    }
+}
+
+/// Largest integer an [f64] represents exactly (2^53).
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(a: Vec<Value>) -> Self {
+        Value::Array(a)
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Value {
+    fn from(o: BTreeMap<String, Value>) -> Self {
+        Value::Object(o)
+    }
+}
+
+/// Serializes to compact JSON. Numbers that are not finite have no
+/// JSON representation and are written as null.
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) if !n.is_finite() => f.write_str("null"),
+            Value::Number(n) if n.fract() == 0.0 && n.abs() <= MAX_SAFE_INTEGER => {
+                write!(f, "{}", *n as i64)
+            }
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write_string(s, f),
+            Value::Array(a) => {
+                f.write_char('[')?;
+                for (i, value) in a.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                f.write_char(']')
+            }
+            Value::Object(o) => {
+                f.write_char('{')?;
+                for (i, (key, value)) in o.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    write_string(key, f)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// Private helper to write a quoted and escaped string.
+fn write_string(s: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{8}' => f.write_str("\\b")?,
+            '\u{c}' => f.write_str("\\f")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?
+        }
+    }
+    f.write_char('"')
+}
+
+/// Private recursive descent parser over the bytes of a JSON text.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError { position: self.position, message }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str, value: Value) -> Result<Value, ParseError> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Result::Ok(value)
+        } else {
+            Result::Err(self.error("invalid literal"))
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, ParseError> {
+        if depth > MAX_DEPTH {
+            return Result::Err(self.error("nesting too deep"))
+        }
+
+        match self.peek() {
+            None => Result::Err(self.error("unexpected end of input")),
+            Some(b'n') => self.expect_literal("null", Value::Null),
+            Some(b't') => self.expect_literal("true", Value::Bool(true)),
+            Some(b'f') => self.expect_literal("false", Value::Bool(false)),
+            Some(b'"') => Result::Ok(Value::String(self.parse_string()?)),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'{') => self.parse_object(depth),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Result::Err(self.error("unexpected character"))
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Value, ParseError> {
+        self.position += 1; // [
+        let mut array = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.position += 1;
+            return Result::Ok(Value::Array(array))
+        }
+
+        loop {
+            self.skip_whitespace();
+            array.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Result::Ok(Value::Array(array))
+                }
+                _ => return Result::Err(self.error("expected ',' or ']'"))
+            }
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Value, ParseError> {
+        self.position += 1; // {
+        let mut object = BTreeMap::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.position += 1;
+            return Result::Ok(Value::Object(object))
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Result::Err(self.error("expected a string key"))
+            }
+            let key_position = self.position;
+            let key = self.parse_string()?;
+
+            self.skip_whitespace();
+            if self.peek() != Some(b':') {
+                return Result::Err(self.error("expected ':'"))
+            }
+            self.position += 1;
+            self.skip_whitespace();
+
+            let value = self.parse_value(depth + 1)?;
+            if object.insert(key, value).is_some() {
+                return Result::Err(ParseError { position: key_position, message: "duplicate key" })
+            }
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Result::Ok(Value::Object(object))
+                }
+                _ => return Result::Err(self.error("expected ',' or '}'"))
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ParseError> {
+        let start = self.position;
+        let digits = |parser: &mut Parser| -> usize {
+            let from = parser.position;
+            while let Some(b'0'..=b'9') = parser.peek() {
+                parser.position += 1;
+            }
+            parser.position - from
+        };
+
+        if self.peek() == Some(b'-') {
+            self.position += 1;
+        }
+
+        // Integer part: a single zero or digits without a leading zero
+        match self.peek() {
+            Some(b'0') => self.position += 1,
+            Some(b'1'..=b'9') => {
+                digits(self);
+            }
+            _ => return Result::Err(self.error("expected a digit"))
+        }
+
+        if self.peek() == Some(b'.') {
+            self.position += 1;
+            if digits(self) == 0 {
+                return Result::Err(self.error("expected a digit"))
+            }
+        }
+
+        if let Some(b'e' | b'E') = self.peek() {
+            self.position += 1;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.position += 1;
+            }
+            if digits(self) == 0 {
+                return Result::Err(self.error("expected a digit"))
+            }
+        }
+
+        // Only ASCII was consumed, so the slice is valid UTF-8
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap();
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Result::Ok(Value::Number(n)),
+            _ => Result::Err(ParseError { position: start, message: "number out of range" })
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let hex = self.bytes.get(self.position..self.position + 4)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+
+        match hex {
+            Some(code) => {
+                self.position += 4;
+                Result::Ok(code)
+            }
+            None => Result::Err(self.error("invalid unicode escape"))
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.position += 1; // "
+        let mut string = String::new();
+
+        loop {
+            // Copy the run of plain characters at once
+            let run_start = self.position;
+            while let Some(b) = self.peek() {
+                if b == b'"' || b == b'\\' || b < 0x20 {
+                    break;
+                }
+                self.position += 1;
+            }
+            // Input is a &str and the run ends on an ASCII byte, so it's valid UTF-8
+            string.push_str(std::str::from_utf8(&self.bytes[run_start..self.position]).unwrap());
+
+            match self.peek() {
+                None => return Result::Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.position += 1;
+                    return Result::Ok(string)
+                }
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.position += 1;
+                            let escape_position = self.position;
+                            let high = self.parse_hex4()?;
+                            let code = match high {
+                                0xD800..=0xDBFF => {
+                                    if !self.bytes[self.position..].starts_with(b"\\u") {
+                                        return Result::Err(self.error("lone surrogate"))
+                                    }
+                                    self.position += 2;
+                                    let low = self.parse_hex4()?;
+                                    if !(0xDC00..=0xDFFF).contains(&low) {
+                                        return Result::Err(self.error("lone surrogate"))
+                                    }
+                                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                                }
+                                0xDC00..=0xDFFF => {
+                                    return Result::Err(
+                                        ParseError { position: escape_position, message: "lone surrogate" }
+                                    )
+                                }
+                                code => code
+                            };
+                            // Surrogates were handled above, so the code is a valid char
+                            string.push(char::from_u32(code).unwrap());
+                            continue;
+                        }
+                        _ => return Result::Err(self.error("invalid escape"))
+                    };
+                    self.position += 1;
+                    string.push(escaped);
+                }
+                Some(_) => return Result::Err(self.error("unescaped control character"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use crate::json::Value;
+
+#[test]
+/// Test [Value] parse method in normal operation.
+fn parse() {
+    let value = Value::parse(
+        " {\"a\": [1, -2.5, 3e2, 0, -0.0e-1], \"b\": {\"c\": null}, \"d\": true, \"e\": false, \"f\": \"x\"} \r\n"
+    ).unwrap();
+
+    let a = value.get("a").and_then(Value::as_array).unwrap();
+    assert!(a.iter().map(|n| n.as_f64().unwrap()).collect::<Vec<_>>() == vec![1.0, -2.5, 300.0, 0.0, 0.0]);
+    assert!(value.get("b").and_then(|b| b.get("c")).unwrap().is_null());
+    assert!(value.get("d").and_then(Value::as_bool) == Some(true));
+    assert!(value.get("e").and_then(Value::as_bool) == Some(false));
+    assert!(value.get("f").and_then(Value::as_str) == Some("x"));
+    assert!(value.get("g").is_none());
+
+    assert!(Value::parse("\"\\\"\\\\\\/\\b\\f\\n\\r\\t\\u00e9\\ud83d\\ude00\"").unwrap()
+        == Value::from("\"\\/\u{8}\u{c}\n\r\té😀"));
+    assert!(Value::parse("\"kočka\"").unwrap() == Value::from("kočka"));
+}
+
+#[test]
+/// Test [Value] parse method with input that is not strict JSON.
+fn parse_fail() {
+    let cases = [
+        "",
+        " ",
+        "[1, 2,]",
+        "{\"a\": 1,}",
+        "{'a': 1}",
+        "{a: 1}",
+        "[1] [2]",
+        "01",
+        "-",
+        "1.",
+        ".5",
+        "1e",
+        "+1",
+        "1e999",
+        "NaN",
+        "nul",
+        "True",
+        "\"unterminated",
+        "\"tab\tinside\"",
+        "\"\\x\"",
+        "\"\\u12\"",
+        "\"\\ud83d\"",
+        "\"\\ude00\"",
+        "{\"a\": 1, \"a\": 2}",
+        "[1 2]",
+        "/* comment */ 1"
+    ];
+
+    for text in cases {
+        assert!(Value::parse(text).is_err(), "{text:?} must be rejected");
+    }
+
+    let deep = "[".repeat(1000) + &"]".repeat(1000);
+    assert!(Value::parse(&deep).unwrap_err().get_message() == "nesting too deep");
+
+    let err = Value::parse("[1, x]").unwrap_err();
+    assert!(err.get_position() == 4);
+    assert!(err.to_string() == "unexpected character at byte 4");
+}
+
+#[test]
+/// Test [Value] serialization and that it parses back to the same value.
+fn serialize() {
+    let mut object = BTreeMap::new();
+    object.insert("z".to_string(), Value::from(1_i64));
+    object.insert("a".to_string(), Value::from(vec![
+        Value::Null,
+        Value::from(true),
+        Value::from(0.5),
+        Value::from(-3_i64),
+        Value::from(f64::NAN)
+    ]));
+    object.insert("s".to_string(), Value::from("quote \" slash \\ newline \n bell \u{7} é"));
+    let value = Value::from(object);
+
+    let text = value.to_string();
+    assert!(text == "{\"a\":[null,true,0.5,-3,null],\"s\":\"quote \\\" slash \\\\ newline \\n bell \\u0007 é\",\"z\":1}");
+
+    let parsed = Value::parse(&text).unwrap();
+    assert!(parsed.get("s") == value.get("s"));
+    assert!(parsed.get("z").and_then(Value::as_i64) == Some(1));
+
+    assert!(Value::from(1e300).to_string().parse::<f64>().unwrap() == 1e300);
+    assert!(Value::from(2.5).as_i64().is_none());
+}
@@ -2,5 +2,11 @@ pub mod worker_pool;
 
 pub mod web;
 
+pub mod json;
+
+pub mod log;
+
+pub mod date;
+
 #[cfg(test)]
 mod tests {}
@@ -0,0 +1,303 @@
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, Error, LineWriter, Write},
+    path::Path,
+    sync::{atomic::{AtomicU8, Ordering}, Arc, Mutex, RwLock},
+    time::SystemTime
+};
+
+use crate::date::DateTime;
+
+/// Severity of a [Record], ordered from the most verbose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Trace => write!(f, "TRACE"),
+            Level::Debug => write!(f, "DEBUG"),
+            Level::Info => write!(f, "INFO"),
+            Level::Warn => write!(f, "WARN"),
+            Level::Error => write!(f, "ERROR")
+        }
+    }
+}
+
+/// One log event: a message and key/value fields, e.g.
+/// "received a job" with worker=3.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    time: SystemTime,
+    level: Level,
+    target: String,
+    message: String,
+    fields: Vec<(String, String)>
+}
+
+impl Record {
+    /// Private helper for a record made now, with the fields formatted.
+    fn new(level: Level, target: &str, message: &str, fields: &[(&str, &dyn Display)]) -> Record {
+        Record {
+            time: SystemTime::now(),
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+            fields: fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        }
+    }
+
+    pub const fn get_time(&self) -> SystemTime {
+        self.time
+    }
+
+    pub const fn get_level(&self) -> Level {
+        self.level
+    }
+
+    /// Module the record comes from, e.g. "rns::worker_pool".
+    pub const fn get_target(&self) -> &String {
+        &self.target
+    }
+
+    pub const fn get_message(&self) -> &String {
+        &self.message
+    }
+
+    pub const fn get_fields(&self) -> &Vec<(String, String)> {
+        &self.fields
+    }
+
+    /// Returns the value of the first field named [key].
+    pub fn get_field(&self, key: &str) -> Option<&String> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Formats as a logfmt style line without the line break, e.g.
+    /// "2024-02-28T23:59:59Z WARN rns::web: could not accept a connection error=\"...\"".
+    /// Values with spaces, quotes or control characters are quoted.
+    pub fn to_line(&self) -> String {
+        let mut line = format!(
+            "{} {} {}: {}",
+            DateTime::from_system_time(self.time).to_rfc3339_str(),
+            self.level,
+            self.target,
+            self.message
+        );
+        for (key, value) in &self.fields {
+            let plain = !value.is_empty() && !value.chars().any(
+                |c| c.is_whitespace() || c.is_control() || c == '"' || c == '='
+            );
+            if plain {
+                line.push_str(&format!(" {key}={value}"));
+            } else {
+                line.push_str(&format!(" {key}={value:?}"));
+            }
+        }
+        line
+    }
+}
+
+/// Destination of [Record]s. Sinks are shared between threads,
+/// so they must be safe to write concurrently.
+pub trait Sink: Send + Sync {
+    fn write(&self, record: &Record);
+}
+
+/// Writes lines to the standard error.
+pub struct StderrSink;
+
+impl Sink for StderrSink {
+    fn write(&self, record: &Record) {
+        // Nowhere left to report a failure to
+        let _ = writeln!(io::stderr().lock(), "{}", record.to_line());
+    }
+}
+
+/// Appends lines to a file.
+pub struct FileSink {
+    file: Mutex<LineWriter<File>>
+}
+
+impl FileSink {
+    /// Appends to the file at [path], creating it if it does not exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<FileSink, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Result::Ok(
+            FileSink {
+                file: Mutex::new(LineWriter::new(file))
+            }
+        )
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&self, record: &Record) {
+        let mut file = self.file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(file, "{}", record.to_line());
+    }
+}
+
+/// Keeps records in memory, for tests to inspect.
+#[derive(Default)]
+pub struct MemorySink {
+    records: Mutex<Vec<Record>>
+}
+
+impl MemorySink {
+    pub fn new() -> MemorySink {
+        MemorySink::default()
+    }
+
+    /// Returns a copy of the records written so far.
+    pub fn records(&self) -> Vec<Record> {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+    }
+}
+
+impl Sink for MemorySink {
+    fn write(&self, record: &Record) {
+        self.records.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).push(record.clone());
+    }
+}
+
+/// Filters records by level and hands them to every sink.
+/// Install one for the whole crate with [set_logger], or use it directly.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+///
+/// use rns::log::{self, Level, Logger, MemorySink, StderrSink};
+///
+/// let memory = Arc::new(MemorySink::new());
+/// let logger = Logger::new(Level::Info)
+///     .sink(Arc::new(StderrSink))
+///     .sink(memory.clone());
+///
+/// logger.log(Level::Info, "app", "listening", &[("port", &8080)]);
+/// logger.log(Level::Debug, "app", "filtered out", &[]);
+/// assert!(memory.records().len() == 1);
+///
+/// log::set_logger(logger);
+/// ```
+pub struct Logger {
+    min_level: Level,
+    sinks: Vec<Arc<dyn Sink>>
+}
+
+/// Warnings and errors to stderr, as used until [set_logger] is called.
+impl Default for Logger {
+    fn default() -> Self {
+        Logger::new(Level::Warn).sink(Arc::new(StderrSink))
+    }
+}
+
+impl Logger {
+    /// A logger without sinks that drops records below [min_level].
+    pub fn new(min_level: Level) -> Logger {
+        Logger {
+            min_level,
+            sinks: Vec::new()
+        }
+    }
+
+    pub fn sink(mut self, sink: Arc<dyn Sink>) -> Logger {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub const fn get_min_level(&self) -> Level {
+        self.min_level
+    }
+
+    pub fn enabled(&self, level: Level) -> bool {
+        level >= self.min_level
+    }
+
+    /// Writes a record to every sink, if [level] is enabled.
+    /// Fields are only formatted for enabled levels.
+    ///
+    /// # Parameters
+    /// level - severity of the record.
+    /// target - where it comes from, usually [module_path].
+    /// message - a fixed description of the event, details go to fields.
+    /// fields - key/value pairs.
+    pub fn log(&self, level: Level, target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+        if !self.enabled(level) {
+            return
+        }
+
+        let record = Record::new(level, target, message, fields);
+        for sink in &self.sinks {
+            sink.write(&record);
+        }
+    }
+}
+
+/// Crate wide [Logger], None until [set_logger] is called.
+static LOGGER: RwLock<Option<Logger>> = RwLock::new(None);
+/// Copy of the minimum level, so disabled records skip the lock.
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+
+/// Replaces the logger used by the crate (and by [log] and the
+/// shorthands such as [info]) for all threads.
+/// Returns the replaced logger, None if the default was in use.
+pub fn set_logger(logger: Logger) -> Option<Logger> {
+    let mut global = LOGGER.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    MIN_LEVEL.store(logger.get_min_level() as u8, Ordering::Relaxed);
+    global.replace(logger)
+}
+
+/// Whether the crate wide logger writes records of [level].
+pub fn enabled(level: Level) -> bool {
+    level as u8 >= MIN_LEVEL.load(Ordering::Relaxed)
+}
+
+/// Same as [Logger::log] with the crate wide logger.
+pub fn log(level: Level, target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+    if !enabled(level) {
+        return
+    }
+
+    let global = LOGGER.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match global.as_ref() {
+        Some(logger) => logger.log(level, target, message, fields),
+        // Same as Logger::default(), MIN_LEVEL already filtered below Warn
+        None => StderrSink.write(&Record::new(level, target, message, fields))
+    }
+}
+
+pub fn trace(target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Trace, target, message, fields);
+}
+
+pub fn debug(target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Debug, target, message, fields);
+}
+
+pub fn info(target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Info, target, message, fields);
+}
+
+pub fn warn(target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Warn, target, message, fields);
+}
+
+pub fn error(target: &str, message: &str, fields: &[(&str, &dyn Display)]) {
+    log(Level::Error, target, message, fields);
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,104 @@
+use std::{env, fs, sync::Arc, time::{Duration, UNIX_EPOCH}};
+
+use crate::log::{self, FileSink, Level, Logger, MemorySink, Record};
+use crate::worker_pool::Pool;
+
+#[test]
+/// Test that [Logger] filters by level and passes fields to every sink.
+fn levels_and_fields() {
+    let first = Arc::new(MemorySink::new());
+    let second = Arc::new(MemorySink::new());
+    let logger = Logger::new(Level::Info).sink(first.clone()).sink(second.clone());
+
+    assert!(!logger.enabled(Level::Debug));
+    assert!(logger.enabled(Level::Error));
+
+    logger.log(Level::Debug, "test", "dropped", &[]);
+    logger.log(Level::Warn, "test", "slow request", &[("path", &"/a"), ("ms", &250)]);
+
+    let records = first.records();
+    assert!(records.len() == 1);
+    assert!(records == second.records());
+    assert!(records[0].get_level() == Level::Warn);
+    assert!(records[0].get_target() == "test");
+    assert!(records[0].get_message() == "slow request");
+    assert!(records[0].get_field("ms").is_some_and(|ms| ms == "250"));
+
+    first.clear();
+    assert!(first.records().is_empty());
+}
+
+#[test]
+/// Test the line format, including values that need quoting.
+fn line_format() {
+    let record = Record {
+        time: UNIX_EPOCH + Duration::from_secs(784_111_777),
+        level: Level::Error,
+        target: "rns::web".to_string(),
+        message: "could not save a session".to_string(),
+        fields: vec![
+            ("worker".to_string(), "3".to_string()),
+            ("error".to_string(), "disk \"full\"\n".to_string()),
+            ("empty".to_string(), String::new())
+        ]
+    };
+
+    assert!(record.to_line() == concat!(
+        "1994-11-06T08:49:37Z ERROR rns::web: could not save a session ",
+        "worker=3 error=\"disk \\\"full\\\"\\n\" empty=\"\""
+    ));
+}
+
+#[test]
+/// Test that [FileSink] appends lines.
+fn file_sink() {
+    let path = env::temp_dir().join(format!("rns-log-{}.log", std::process::id()));
+    let sink = Arc::new(FileSink::new(&path).unwrap());
+    let logger = Logger::new(Level::Trace).sink(sink.clone());
+
+    logger.log(Level::Trace, "test", "first", &[]);
+    logger.log(Level::Info, "test", "second", &[]);
+    drop(logger);
+    drop(sink);
+
+    let text = fs::read_to_string(&path).unwrap();
+    let lines: Vec<_> = text.lines().collect();
+    assert!(lines.len() == 2);
+    assert!(lines[0].ends_with(" TRACE test: first"));
+    assert!(lines[1].ends_with(" INFO test: second"));
+
+    fs::remove_file(path).unwrap();
+}
+
+/// Private guard that puts the replaced crate wide logger back,
+/// even if the test fails.
+struct RestoreLogger(Option<Logger>);
+
+impl Drop for RestoreLogger {
+    fn drop(&mut self) {
+        log::set_logger(self.0.take().unwrap_or_default());
+    }
+}
+
+#[test]
+/// Test that [Pool] diagnostics go through the crate wide logger.
+/// The only test to set it, since it is shared by all tests. Pools of
+/// tests running in parallel log too, so records are only looked for.
+fn global_logger() {
+    let memory = Arc::new(MemorySink::new());
+    let _restore = RestoreLogger(log::set_logger(Logger::new(Level::Debug).sink(memory.clone())));
+    assert!(log::enabled(Level::Debug));
+    assert!(!log::enabled(Level::Trace));
+
+    let pool = Pool::new(2);
+    pool.execute(|| {});
+    drop(pool);
+
+    let records = memory.records();
+    let from_pool: Vec<_> = records.iter().filter(|r| r.get_target() == "rns::worker_pool").collect();
+    let debug_with_worker = |message: &str| from_pool.iter().filter(|r| {
+        r.get_message() == message && r.get_level() == Level::Debug && r.get_field("worker").is_some()
+    }).count();
+    assert!(debug_with_worker("worker received a job") >= 1);
+    assert!(debug_with_worker("worker shutting down") >= 2);
+}
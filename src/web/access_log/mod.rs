@@ -0,0 +1,203 @@
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::{self, Error, LineWriter, Read, Write},
+    net::IpAddr,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime}
+};
+
+use crate::json::Value;
+use crate::date::DateTime;
+use crate::web::request::RequestBackend;
+use crate::web::response::ResponseCode;
+
+/// Layout of an access log line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    /// host ident authuser [date] "request" status bytes duration
+    Common,
+    /// Common followed by "referer" "user-agent", with the duration last
+    Combined,
+    /// One JSON object per line.
+    Json
+}
+
+/// What is known about a served request when it is logged.
+pub(crate) struct Entry {
+    client: Option<IpAddr>,
+    time: SystemTime,
+    request_line: Option<String>,
+    status: Option<usize>,
+    bytes: u64,
+    referer: Option<String>,
+    user_agent: Option<String>,
+    duration: Duration
+}
+
+impl Entry {
+    /// An entry for a request that was built and processed.
+    pub(crate) fn from_request<T: Read + Write>(
+        request: &RequestBackend<T>,
+        client: Option<IpAddr>,
+        time: SystemTime,
+        duration: Duration
+    ) -> Entry {
+        let header = |name: &str| request.get_header(name).map(|h| h.get_value().clone());
+        Entry {
+            client,
+            time,
+            request_line: Some(
+                format!("{} {} {}", request.get_method(), request.get_uri(), request.get_version())
+            ),
+            status: request.get_response_code().map(ResponseCode::get_code),
+            bytes: request.get_bytes_sent(),
+            referer: header("Referer"),
+            user_agent: header("User-Agent"),
+            duration
+        }
+    }
+
+    /// An entry for a request that was rejected while being read, e.g. with 400 or 408.
+    /// The request line is known unless the request was rejected for it.
+    pub(crate) fn rejected(
+        client: Option<IpAddr>,
+        time: SystemTime,
+        request_line: Option<&str>,
+        code: &ResponseCode,
+        duration: Duration
+    ) -> Entry {
+        Entry {
+            client,
+            time,
+            request_line: request_line.map(str::to_string),
+            status: Some(code.get_code()),
+            bytes: 0,
+            referer: None,
+            user_agent: None,
+            duration
+        }
+    }
+}
+
+/// Writes one line per request to stdout, a file or any other writer.
+/// Lines are written whole, so concurrent workers never interleave them.
+///
+/// # Example
+/// ```no_run
+/// use rns::web::access_log::{AccessLog, LogFormat};
+/// use rns::web::{PooledServer, RouteMap, Server};
+///
+/// let log = AccessLog::file("access.log", LogFormat::Combined).unwrap();
+/// let server = PooledServer::new("127.0.0.1:8080".to_string(), RouteMap::new(), 4)
+///     .with_access_log(log);
+///
+/// server.run().unwrap();
+/// ```
+pub struct AccessLog {
+    format: LogFormat,
+    sink: Mutex<Box<dyn Write + Send>>
+}
+
+impl AccessLog {
+    pub fn new(format: LogFormat, sink: Box<dyn Write + Send>) -> AccessLog {
+        AccessLog {
+            format,
+            sink: Mutex::new(sink)
+        }
+    }
+
+    pub fn stdout(format: LogFormat) -> AccessLog {
+        AccessLog::new(format, Box::new(io::stdout()))
+    }
+
+    /// Appends to the file at [path], creating it if it does not exist.
+    pub fn file<P: AsRef<Path>>(path: P, format: LogFormat) -> Result<AccessLog, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Result::Ok(AccessLog::new(format, Box::new(LineWriter::new(file))))
+    }
+
+    pub const fn get_format(&self) -> LogFormat {
+        self.format
+    }
+
+    /// Formats the entry as a line without the line break.
+    pub(crate) fn format(&self, entry: &Entry) -> String {
+        match self.format {
+            LogFormat::Common => format!("{} {}", common(entry), seconds(entry.duration)),
+            LogFormat::Combined => format!(
+                "{} \"{}\" \"{}\" {}",
+                common(entry),
+                entry.referer.as_deref().map(escape).unwrap_or("-".to_string()),
+                entry.user_agent.as_deref().map(escape).unwrap_or("-".to_string()),
+                seconds(entry.duration)
+            ),
+            LogFormat::Json => json(entry)
+        }
+    }
+
+    /// Writes the entry. A failing sink can't be reported anywhere better,
+    /// so the line is dropped rather than failing the request.
+    pub(crate) fn log(&self, entry: &Entry) {
+        let mut line = self.format(entry);
+        line.push('\n');
+
+        let mut sink = self.sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = sink.write_all(line.as_bytes());
+    }
+}
+
+/// Private helper for the fields shared by Common and Combined formats.
+fn common(entry: &Entry) -> String {
+    format!(
+        "{} - - [{}] \"{}\" {} {}",
+        entry.client.map(|ip| ip.to_string()).unwrap_or("-".to_string()),
+        DateTime::from_system_time(entry.time).to_clf_str(),
+        entry.request_line.as_deref().map(escape).unwrap_or("-".to_string()),
+        entry.status.map(|code| code.to_string()).unwrap_or("-".to_string()),
+        if entry.bytes == 0 { "-".to_string() } else { entry.bytes.to_string() }
+    )
+}
+
+/// Private helper for the duration of the text formats, in seconds with
+/// millisecond resolution as other servers log it.
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+/// Private helper to escape client controlled text inside quotes,
+/// so a request can't forge a line or break the field layout.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+/// Private helper for the JSON format.
+fn json(entry: &Entry) -> String {
+    let optional = |text: &Option<String>| text.as_deref().map(Value::from).unwrap_or(Value::Null);
+
+    let mut object = BTreeMap::new();
+    object.insert("client".to_string(), entry.client.map(|ip| Value::from(ip.to_string())).unwrap_or(Value::Null));
+    object.insert("time".to_string(), Value::from(DateTime::from_system_time(entry.time).to_rfc3339_str()));
+    object.insert("request".to_string(), optional(&entry.request_line));
+    object.insert("status".to_string(), entry.status.map(|code| Value::from(code as i64)).unwrap_or(Value::Null));
+    object.insert("bytes".to_string(), Value::from(entry.bytes as i64));
+    object.insert("referer".to_string(), optional(&entry.referer));
+    object.insert("user_agent".to_string(), optional(&entry.user_agent));
+    object.insert("duration_ms".to_string(), Value::from(entry.duration.as_secs_f64() * 1000.0));
+    Value::from(object).to_string()
+}
+
+#[cfg(test)]
+mod tests;
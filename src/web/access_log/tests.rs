@@ -0,0 +1,97 @@
+use std::{
+    io::{Cursor, Error, Write},
+    net::{IpAddr, Ipv4Addr},
+    sync::{Arc, Mutex},
+    time::{Duration, UNIX_EPOCH}
+};
+
+use crate::json::Value;
+use crate::web::access_log::{AccessLog, Entry, LogFormat};
+use crate::web::request::RequestBackend;
+use crate::web::response::{Response, ResponseCode};
+
+/// Sink that keeps the written lines for inspection.
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Result::Ok(())
+    }
+}
+
+const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+
+/// An [Entry] for a request answered with "hello".
+fn entry(request_str: &str) -> Entry {
+    let stream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let mut req = RequestBackend::build(stream).unwrap();
+    req.respond(&Response::text("hello")).unwrap();
+
+    Entry::from_request(&req, Some(CLIENT), UNIX_EPOCH + Duration::from_secs(784_111_777), Duration::from_millis(3))
+}
+
+#[test]
+/// Test the Common and Combined formats.
+fn clf() {
+    let request_str = "GET /a?b=1 HTTP/1.1\r\nReferer: https://example.com/\r\nUser-Agent: test \"agent\"\r\n\r\n";
+    let entry = entry(request_str);
+
+    let log = AccessLog::new(LogFormat::Common, Box::new(SharedBuffer::default()));
+    assert!(log.format(&entry) == "127.0.0.1 - - [06/Nov/1994:08:49:37 +0000] \"GET /a?b=1 HTTP/1.1\" 200 5 0.003");
+
+    let log = AccessLog::new(LogFormat::Combined, Box::new(SharedBuffer::default()));
+    assert!(log.format(&entry) == concat!(
+        "127.0.0.1 - - [06/Nov/1994:08:49:37 +0000] \"GET /a?b=1 HTTP/1.1\" 200 5 ",
+        "\"https://example.com/\" \"test \\\"agent\\\"\" 0.003"
+    ));
+
+    // Unknown fields are dashes
+    let entry = Entry::rejected(None, UNIX_EPOCH, None, &ResponseCode::get_408(), Duration::ZERO);
+    assert!(log.format(&entry) == "- - - [01/Jan/1970:00:00:00 +0000] \"-\" 408 - \"-\" \"-\" 0.000");
+
+    // A request rejected after its request line still tells it
+    let entry = Entry::rejected(
+        None, UNIX_EPOCH, Some("POST /upload HTTP/1.1"), &ResponseCode::get_413(), Duration::from_millis(1500)
+    );
+    assert!(log.format(&entry) == "- - - [01/Jan/1970:00:00:00 +0000] \"POST /upload HTTP/1.1\" 413 - \"-\" \"-\" 1.500");
+}
+
+#[test]
+/// Test the JSON-lines format and that lines reach the sink.
+fn json_lines() {
+    let buffer = SharedBuffer::default();
+    let log = AccessLog::new(LogFormat::Json, Box::new(buffer.clone()));
+
+    log.log(&entry("HEAD / HTTP/1.0\r\n\r\n"));
+    log.log(&Entry::rejected(Some(CLIENT), UNIX_EPOCH, None, &ResponseCode::get_400(), Duration::ZERO));
+
+    let written = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<_> = written.lines().map(|line| Value::parse(line).unwrap()).collect();
+    assert!(lines.len() == 2);
+
+    let first = &lines[0];
+    assert!(first.get("client").and_then(Value::as_str) == Some("127.0.0.1"));
+    assert!(first.get("time").and_then(Value::as_str) == Some("1994-11-06T08:49:37Z"));
+    assert!(first.get("request").and_then(Value::as_str) == Some("HEAD / HTTP/1.0"));
+    assert!(first.get("status").and_then(Value::as_i64) == Some(200));
+    // HEAD sends no body
+    assert!(first.get("bytes").and_then(Value::as_i64) == Some(0));
+    assert!(first.get("duration_ms").and_then(Value::as_f64) == Some(3.0));
+    assert!(first.get("referer") == Some(&Value::Null));
+
+    assert!(lines[1].get("status").and_then(Value::as_i64) == Some(400));
+    assert!(lines[1].get("request") == Some(&Value::Null));
+}
+
+#[test]
+/// Test that control characters can't forge log lines.
+fn escaping() {
+    let entry = entry("GET / HTTP/1.1\r\nUser-Agent: a\x1b[31m\\b\r\n\r\n");
+    let log = AccessLog::new(LogFormat::Combined, Box::new(SharedBuffer::default()));
+    assert!(log.format(&entry).ends_with("\"-\" \"a\\x1b[31m\\\\b\" 0.003"));
+}
@@ -0,0 +1,242 @@
+use std::{fmt::Display, time::{Duration, SystemTime}};
+
+use crate::date::DateTime;
+use crate::web::response::{Header, ResponseCode, WebResult};
+
+/// Checks the RFC 6265 cookie-name grammar (an RFC 2616 token).
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| {
+        b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b)
+    })
+}
+
+/// Checks the RFC 6265 cookie-value grammar: cookie-octets,
+/// optionally wrapped in double quotes.
+pub fn is_valid_value(value: &str) -> bool {
+    let value = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value);
+
+    value.bytes().all(|b| {
+        matches!(b, 0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E)
+    })
+}
+
+/// Checks attribute values such as Path and Domain: no control characters or ";".
+fn is_valid_attribute(value: &str) -> bool {
+    value.bytes().all(|b| (0x20..0x7F).contains(&b) && b != b';')
+}
+
+/// A cookie sent by the client in the Cookie header.
+#[derive(Debug, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String
+}
+
+impl Cookie {
+    /// Parses the value of a Cookie header, e.g. "a=1; b=2".
+    /// Pairs that do not follow the RFC 6265 grammar are skipped,
+    /// so one malformed cookie doesn't reject the whole request.
+    pub fn parse_header(header_value: &str) -> Vec<Cookie> {
+        header_value
+            .split(';')
+            .filter_map(|pair| {
+                let (name, value) = pair.trim().split_once('=')?;
+                if !is_valid_name(name) || !is_valid_value(value) {
+                    return None
+                }
+
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|v| v.strip_suffix('"'))
+                    .unwrap_or(value);
+
+                Some(
+                    Cookie {
+                        name: name.to_string(),
+                        value: value.to_string()
+                    }
+                )
+            })
+            .collect()
+    }
+
+    pub const fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub const fn get_value(&self) -> &String {
+        &self.value
+    }
+}
+
+/// Values of the SameSite attribute.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SameSite::Strict => write!(f, "Strict"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::None => write!(f, "None")
+        }
+    }
+}
+
+/// Builder of a Set-Cookie header. Everything is validated in [build],
+/// so bad input can't produce a broken header.
+/// 
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// 
+/// use rns::web::cookie::{SameSite, SetCookie};
+/// 
+/// let header = SetCookie::new("theme", "dark")
+///     .path("/")
+///     .max_age(Duration::from_secs(3600))
+///     .secure(true)
+///     .http_only(true)
+///     .same_site(SameSite::Lax)
+///     .build()
+///     .unwrap();
+/// 
+/// assert!(header.get_value() == "theme=dark; Path=/; Max-Age=3600; Secure; HttpOnly; SameSite=Lax");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SetCookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>
+}
+
+impl SetCookie {
+    pub fn new(name: &str, value: &str) -> SetCookie {
+        SetCookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None
+        }
+    }
+
+    /// A cookie that makes the client delete [name] right away.
+    /// Path and Domain must match the ones the cookie was set with.
+    pub fn removal(name: &str) -> SetCookie {
+        SetCookie::new(name, "")
+            .max_age(Duration::ZERO)
+            .expires(SystemTime::UNIX_EPOCH)
+    }
+
+    pub fn path(mut self, path: &str) -> SetCookie {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn domain(mut self, domain: &str) -> SetCookie {
+        self.domain = Some(domain.to_string());
+        self
+    }
+
+    /// Sent in whole seconds.
+    pub fn max_age(mut self, max_age: Duration) -> SetCookie {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn expires(mut self, expires: SystemTime) -> SetCookie {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> SetCookie {
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> SetCookie {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> SetCookie {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub const fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    pub const fn get_value(&self) -> &String {
+        &self.value
+    }
+
+    /// Builds the Set-Cookie header.
+    /// Returns [ResponseCode] of 500 (HTTP 500), since an invalid cookie is
+    /// a server side bug, when:
+    /// 1. name or value don't follow the RFC 6265 grammar;
+    /// 2. Path or Domain contain control characters or ";";
+    /// 3. SameSite=None is used without Secure, which browsers reject.
+    pub fn build(&self) -> WebResult<Header> {
+        let attributes_valid = [&self.path, &self.domain]
+            .iter()
+            .all(|attribute| attribute.as_deref().is_none_or(is_valid_attribute));
+        let same_site_valid = self.same_site != Some(SameSite::None) || self.secure;
+
+        if !is_valid_name(&self.name) || !is_valid_value(&self.value)
+            || !attributes_valid || !same_site_valid {
+            return Result::Err(
+                ResponseCode::get_500()
+            )
+        }
+
+        let mut value = format!("{}={}", self.name, self.value);
+        if let Some(path) = &self.path {
+            value.push_str(&format!("; Path={path}"));
+        }
+        if let Some(domain) = &self.domain {
+            value.push_str(&format!("; Domain={domain}"));
+        }
+        if let Some(max_age) = self.max_age {
+            value.push_str(&format!("; Max-Age={}", max_age.as_secs()));
+        }
+        if let Some(expires) = self.expires {
+            value.push_str(&format!("; Expires={}", DateTime::from_system_time(expires).to_http_str()));
+        }
+        if self.secure {
+            value.push_str("; Secure");
+        }
+        if self.http_only {
+            value.push_str("; HttpOnly");
+        }
+        if let Some(same_site) = self.same_site {
+            value.push_str(&format!("; SameSite={same_site}"));
+        }
+
+        Result::Ok(
+            Header::new("Set-Cookie", &value)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests;
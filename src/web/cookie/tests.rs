@@ -0,0 +1,81 @@
+use std::time::{Duration, SystemTime};
+
+use crate::web::{cookie::{is_valid_name, is_valid_value, Cookie, SameSite, SetCookie}, response::ResponseCode};
+
+#[test]
+/// Test [Cookie] parse_header method, including malformed pairs.
+fn parse_header() {
+    let cookies = Cookie::parse_header("session=abc123; theme=\"dark\";empty=; bad name=1; noeq; x=a,b; last=1");
+    let pairs: Vec<_> = cookies.iter().map(|c| (c.get_name().as_str(), c.get_value().as_str())).collect();
+
+    assert!(pairs == vec![
+        ("session", "abc123"),
+        ("theme", "dark"),
+        ("empty", ""),
+        ("last", "1")
+    ]);
+}
+
+#[test]
+/// Test the RFC 6265 grammar checks.
+fn grammar() {
+    assert!(is_valid_name("session_id"));
+    assert!(is_valid_name("__Host-id"));
+    assert!(!is_valid_name(""));
+    assert!(!is_valid_name("a b"));
+    assert!(!is_valid_name("a=b"));
+    assert!(!is_valid_name("a;b"));
+    assert!(!is_valid_name("é"));
+
+    assert!(is_valid_value(""));
+    assert!(is_valid_value("abc-123_./:"));
+    assert!(is_valid_value("\"quoted\""));
+    assert!(!is_valid_value("a b"));
+    assert!(!is_valid_value("a;b"));
+    assert!(!is_valid_value("a,b"));
+    assert!(!is_valid_value("a\\b"));
+    assert!(!is_valid_value("\"a\"b\""));
+    assert!(!is_valid_value("a\r\nSet-Cookie: x=1"));
+}
+
+#[test]
+/// Test [SetCookie] build method with all attributes.
+fn set_cookie() {
+    let header = SetCookie::new("id", "a1")
+        .path("/app")
+        .domain("example.com")
+        .max_age(Duration::from_millis(90_500))
+        .expires(SystemTime::UNIX_EPOCH + Duration::from_secs(784_111_777))
+        .secure(true)
+        .http_only(true)
+        .same_site(SameSite::None)
+        .build()
+        .unwrap();
+
+    assert!(header.get_name() == "Set-Cookie");
+    assert!(header.get_value() == "id=a1; Path=/app; Domain=example.com; Max-Age=90; \
+        Expires=Sun, 06 Nov 1994 08:49:37 GMT; Secure; HttpOnly; SameSite=None");
+
+    let header = SetCookie::new("id", "a1").build().unwrap();
+    assert!(header.get_value() == "id=a1");
+
+    let header = SetCookie::removal("id").path("/").build().unwrap();
+    assert!(header.get_value() == "id=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+}
+
+#[test]
+/// Test [SetCookie] build method with input that would break the header.
+fn set_cookie_fail() {
+    let cases = [
+        SetCookie::new("bad name", "1"),
+        SetCookie::new("id", "1; Domain=evil.com"),
+        SetCookie::new("id", "line\r\nbreak"),
+        SetCookie::new("id", "1").path("/; Secure"),
+        SetCookie::new("id", "1").domain("a.com\n"),
+        SetCookie::new("id", "1").same_site(SameSite::None)
+    ];
+
+    for cookie in cases {
+        assert!(cookie.build().unwrap_err() == ResponseCode::get_500(), "{cookie:?} must be rejected");
+    }
+}
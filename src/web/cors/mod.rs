@@ -0,0 +1,201 @@
+use std::{io::{Read, Write}, time::Duration};
+
+use crate::web::request::RequestBackend;
+use crate::web::response::{Header, Response, ResponseCode};
+use crate::web::RouteMap;
+
+/// An origin accepted by [CorsConfig].
+#[derive(Debug, Clone)]
+enum OriginRule {
+    Exact(String),
+    /// Prefix and suffix around a single wildcard.
+    Pattern(String, String),
+    Any
+}
+
+impl OriginRule {
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginRule::Exact(allowed) => allowed.eq_ignore_ascii_case(origin),
+            OriginRule::Pattern(prefix, suffix) => {
+                let origin = origin.to_ascii_lowercase();
+                origin.len() > prefix.len() + suffix.len()
+                    && origin.starts_with(prefix.as_str())
+                    && origin.ends_with(suffix.as_str())
+                    // The wildcard stands for host labels only, so it can't
+                    // swallow a port, a scheme or another host
+                    && origin[prefix.len()..origin.len() - suffix.len()].bytes().all(
+                        |b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.'
+                    )
+            }
+            OriginRule::Any => true
+        }
+    }
+}
+
+/// Cross-Origin Resource Sharing policy for [PooledServer::with_cors](super::PooledServer::with_cors).
+/// Allowed origins are always echoed back (never "*"), together with
+/// "Vary: Origin", so the policy also works with credentials.
+/// Preflight requests are answered by the server with the methods registered
+/// in the [RouteMap] for the URI, no OPTIONS route is needed.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+///
+/// use rns::web::cors::CorsConfig;
+///
+/// let cors = CorsConfig::new()
+///     .allow_origin("https://app.example.com")
+///     .allow_origin_pattern("https://*.example.org")
+///     .allow_headers(&["Content-Type", "Authorization"])
+///     .expose_headers(&["X-Request-Id"])
+///     .allow_credentials(true)
+///     .max_age(Duration::from_secs(600));
+///
+/// assert!(cors.is_allowed("https://api.example.org"));
+/// assert!(!cors.is_allowed("https://example.org"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    origins: Vec<OriginRule>,
+    allow_headers: Vec<String>,
+    expose_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: Option<Duration>
+}
+
+impl CorsConfig {
+    /// A policy that allows no origins until some are added.
+    pub fn new() -> CorsConfig {
+        CorsConfig::default()
+    }
+
+    /// Allows a single origin, e.g. "https://app.example.com".
+    pub fn allow_origin(mut self, origin: &str) -> CorsConfig {
+        self.origins.push(OriginRule::Exact(origin.to_string()));
+        self
+    }
+
+    /// Allows origins matching a pattern with one "*" standing for
+    /// one or more host labels, e.g. "https://*.example.com".
+    ///
+    /// # Panics
+    /// Will panic if the pattern has more than one "*".
+    pub fn allow_origin_pattern(mut self, pattern: &str) -> CorsConfig {
+        let pattern = pattern.to_ascii_lowercase();
+        let rule = match pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                assert!(!suffix.contains('*'), "origin pattern can have only one wildcard");
+                OriginRule::Pattern(prefix.to_string(), suffix.to_string())
+            }
+            None => OriginRule::Exact(pattern)
+        };
+        self.origins.push(rule);
+        self
+    }
+
+    /// Allows every origin. Origins are still echoed, so
+    /// combined with credentials this trusts any site.
+    pub fn allow_any_origin(mut self) -> CorsConfig {
+        self.origins.push(OriginRule::Any);
+        self
+    }
+
+    /// Request headers a preflight may ask for, beyond the CORS-safelisted ones.
+    pub fn allow_headers(mut self, headers: &[&str]) -> CorsConfig {
+        self.allow_headers.extend(headers.iter().map(|h| h.to_string()));
+        self
+    }
+
+    /// Response headers scripts may read, beyond the CORS-safelisted ones.
+    pub fn expose_headers(mut self, headers: &[&str]) -> CorsConfig {
+        self.expose_headers.extend(headers.iter().map(|h| h.to_string()));
+        self
+    }
+
+    /// Lets browsers send cookies and read responses to credentialed requests.
+    pub fn allow_credentials(mut self, allow: bool) -> CorsConfig {
+        self.allow_credentials = allow;
+        self
+    }
+
+    /// How long browsers may cache a preflight response.
+    pub fn max_age(mut self, max_age: Duration) -> CorsConfig {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        self.origins.iter().any(|rule| rule.matches(origin))
+    }
+
+    /// Private helper for the headers every response to an allowed origin carries.
+    fn origin_headers(&self, origin: &str) -> Vec<Header> {
+        let mut headers = vec![Header::new("Access-Control-Allow-Origin", origin)];
+        if self.allow_credentials {
+            headers.push(Header::new("Access-Control-Allow-Credentials", "true"));
+        }
+        headers
+    }
+
+    /// Applies the policy to a request. Preflight requests are answered right away,
+    /// in which case true is returned and the request must not be processed further.
+    /// Other requests from allowed origins get CORS headers added to their response,
+    /// and every response carries Vary: Origin.
+    pub(crate) fn handle<T: Read + Write>(&self, request: &mut RequestBackend<T>, route_map: &RouteMap) -> bool {
+        let Some(origin) = request.get_header("Origin").map(|h| h.get_value().clone()) else {
+            // A cached same-origin response must not be served to a cross-origin request
+            request.add_response_header(Header::new("Vary", "Origin"));
+            return false
+        };
+        let allowed = self.is_allowed(&origin);
+
+        let is_preflight = request.get_method() == "OPTIONS"
+            && request.get_header("Access-Control-Request-Method").is_some();
+        if !is_preflight {
+            if allowed {
+                for header in self.origin_headers(&origin) {
+                    request.add_response_header(header);
+                }
+                if !self.expose_headers.is_empty() {
+                    request.add_response_header(
+                        Header::new("Access-Control-Expose-Headers", &self.expose_headers.join(", "))
+                    );
+                }
+            }
+            // The answer depends on Origin either way, so caches must keep them apart
+            request.add_response_header(Header::new("Vary", "Origin"));
+            return false
+        }
+
+        let response = match route_map.get_methods(request.get_path()) {
+            None => Response::builder().status(ResponseCode::get_404()),
+            Some(_) if !allowed => Response::builder().status(ResponseCode::get_403()),
+            Some(methods) => {
+                let mut builder = Response::builder()
+                    .status(ResponseCode::get_204())
+                    .header("Access-Control-Allow-Methods", &methods.join(", "));
+                for header in self.origin_headers(&origin) {
+                    builder = builder.header(header.get_name(), header.get_value());
+                }
+                if !self.allow_headers.is_empty() {
+                    builder = builder.header("Access-Control-Allow-Headers", &self.allow_headers.join(", "));
+                }
+                if let Some(max_age) = self.max_age {
+                    builder = builder.header("Access-Control-Max-Age", &max_age.as_secs().to_string());
+                }
+                builder
+            }
+        };
+
+        let response = response
+            .header("Vary", "Origin, Access-Control-Request-Method, Access-Control-Request-Headers")
+            .build();
+        let _ = request.respond(&response);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests;
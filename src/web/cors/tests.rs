@@ -0,0 +1,119 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use crate::web::cors::CorsConfig;
+use crate::web::request::{Request, RequestBackend};
+use crate::web::response::ResponseCode;
+use crate::web::RouteMap;
+
+type MockStream = Cursor<Vec<u8>>;
+
+fn route_map() -> RouteMap {
+    fn dummy_function(_request: &mut Request) {}
+
+    let mut route_map = RouteMap::new();
+    route_map.insert_route_methods(
+        "/api".to_string(),
+        &mut vec!["PUT".to_string(), "GET".to_string()],
+        Arc::new(dummy_function)
+    );
+    route_map
+}
+
+/// Runs [CorsConfig] handle on a request, returning whether it was
+/// answered and what was written to the client.
+fn handled(cors: &CorsConfig, request_str: &str) -> (bool, String, RequestBackend<MockStream>) {
+    let stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let mut req = RequestBackend::build(stream).unwrap();
+    let answered = cors.handle(&mut req, &route_map());
+
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    (answered, written, req)
+}
+
+#[test]
+/// Test exact and wildcard origin matching.
+fn origins() {
+    let cors = CorsConfig::new()
+        .allow_origin("https://app.example.com")
+        .allow_origin_pattern("https://*.Example.org");
+
+    assert!(cors.is_allowed("https://app.example.com"));
+    assert!(cors.is_allowed("HTTPS://APP.EXAMPLE.COM"));
+    assert!(!cors.is_allowed("http://app.example.com"));
+    assert!(!cors.is_allowed("https://app.example.com:8443"));
+
+    assert!(cors.is_allowed("https://a.example.org"));
+    assert!(cors.is_allowed("https://a.b-c.example.org"));
+    assert!(!cors.is_allowed("https://.example.org"));
+    assert!(!cors.is_allowed("https://example.org"));
+    assert!(!cors.is_allowed("https://evil.com/.example.org"));
+    assert!(!cors.is_allowed("https://a.example.org.evil.com"));
+
+    assert!(!CorsConfig::new().is_allowed("https://app.example.com"));
+    assert!(CorsConfig::new().allow_any_origin().is_allowed("null"));
+}
+
+#[test]
+/// Test a preflight answered from the methods of the [RouteMap].
+fn preflight() {
+    let cors = CorsConfig::new()
+        .allow_origin("https://app.example.com")
+        .allow_headers(&["Content-Type", "X-Token"])
+        .allow_credentials(true)
+        .max_age(Duration::from_secs(600));
+
+    let (answered, written, _) = handled(&cors, "OPTIONS /api HTTP/1.1\r\nOrigin: https://app.example.com\r\nAccess-Control-Request-Method: PUT\r\n\r\n");
+    assert!(answered);
+    assert!(written.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(written.contains("\r\nAccess-Control-Allow-Methods: GET, HEAD, OPTIONS, PUT\r\n"));
+    assert!(written.contains("\r\nAccess-Control-Allow-Origin: https://app.example.com\r\n"));
+    assert!(written.contains("\r\nAccess-Control-Allow-Credentials: true\r\n"));
+    assert!(written.contains("\r\nAccess-Control-Allow-Headers: Content-Type, X-Token\r\n"));
+    assert!(written.contains("\r\nAccess-Control-Max-Age: 600\r\n"));
+
+    let (answered, written, _) = handled(&cors, "OPTIONS /api HTTP/1.1\r\nOrigin: https://evil.com\r\nAccess-Control-Request-Method: PUT\r\n\r\n");
+    assert!(answered);
+    assert!(written.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    assert!(!written.contains("Access-Control-Allow-Origin"));
+
+    let (answered, written, _) = handled(&cors, "OPTIONS /none HTTP/1.1\r\nOrigin: https://app.example.com\r\nAccess-Control-Request-Method: PUT\r\n\r\n");
+    assert!(answered);
+    assert!(written.starts_with("HTTP/1.1 404 Not Found\r\n"));
+}
+
+#[test]
+/// Test that CORS headers are added to the handler's response.
+fn actual_request() {
+    let cors = CorsConfig::new()
+        .allow_origin("https://app.example.com")
+        .expose_headers(&["X-Request-Id"]);
+
+    let request_str = "GET /api HTTP/1.1\r\nOrigin: https://app.example.com\r\n\r\n";
+    let (answered, written, mut req) = handled(&cors, request_str);
+    assert!(!answered);
+    assert!(written.is_empty());
+
+    req.respond_code(ResponseCode::get_200()).unwrap();
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    assert!(written.contains("\r\nAccess-Control-Allow-Origin: https://app.example.com\r\n"));
+    assert!(written.contains("\r\nAccess-Control-Expose-Headers: X-Request-Id\r\n"));
+    assert!(written.contains("\r\nVary: Origin\r\n"));
+    assert!(!written.contains("Access-Control-Allow-Credentials"));
+
+    // Not a preflight without Access-Control-Request-Method, and not allowed
+    let request_str = "OPTIONS /api HTTP/1.1\r\nOrigin: https://evil.com\r\n\r\n";
+    let (answered, _, mut req) = handled(&cors, request_str);
+    assert!(!answered);
+    req.respond_code(ResponseCode::get_200()).unwrap();
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    assert!(!written.contains("Access-Control-Allow-Origin"));
+
+    // Same-origin requests don't send Origin, the response still varies on it
+    let request_str = "GET /api HTTP/1.1\r\n\r\n";
+    let (answered, _, mut req) = handled(&cors, request_str);
+    assert!(!answered);
+    req.respond_code(ResponseCode::get_200()).unwrap();
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    assert!(written.contains("\r\nVary: Origin\r\n"));
+    assert!(!written.contains("Access-Control-Allow-Origin"));
+}
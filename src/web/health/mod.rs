@@ -0,0 +1,178 @@
+use std::{
+    collections::BTreeMap,
+    io::{Read, Write},
+    sync::{atomic::{AtomicBool, Ordering}, Arc}
+};
+
+use crate::json::Value;
+use crate::web::request::RequestBackend;
+use crate::web::response::{Header, Response, ResponseCode};
+use crate::worker_pool::PoolStats;
+
+/// A readiness check, e.g. a database ping. Returns why it failed.
+pub type Check = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// Liveness and readiness probes for orchestrators, enabled with
+/// [PooledServer::with_health](super::PooledServer::with_health).
+/// The probes are answered before authentication and throttling.
+///
+/// Liveness ("/healthz") succeeds as long as a worker can answer.
+/// Readiness ("/readyz") fails with 503 while the server is draining,
+/// while the worker pool is saturated, or while a registered check fails.
+///
+/// # Example
+/// ```no_run
+/// use rns::web::health::Health;
+/// use rns::web::{PooledServer, RouteMap, Server};
+///
+/// let health = Health::new()
+///     .check("database", || Ok(()))
+///     .max_queued(16);
+///
+/// let server = PooledServer::new("127.0.0.1:8080".to_string(), RouteMap::new(), 4)
+///     .with_health(health);
+///
+/// // Before shutting down, stop receiving new traffic
+/// server.get_health().unwrap().set_draining(true);
+/// ```
+pub struct Health {
+    liveness_path: String,
+    readiness_path: String,
+    checks: Vec<(String, Check)>,
+    max_queued: Option<usize>,
+    draining: AtomicBool,
+    pool: Option<Arc<PoolStats>>
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Health {
+    pub fn new() -> Health {
+        Health {
+            liveness_path: "/healthz".to_string(),
+            readiness_path: "/readyz".to_string(),
+            checks: Vec::new(),
+            max_queued: None,
+            draining: AtomicBool::new(false),
+            pool: None
+        }
+    }
+
+    pub fn liveness_path(mut self, path: &str) -> Health {
+        self.liveness_path = path.to_string();
+        self
+    }
+
+    pub fn readiness_path(mut self, path: &str) -> Health {
+        self.readiness_path = path.to_string();
+        self
+    }
+
+    /// Registers a readiness check. Checks run on every readiness probe,
+    /// in the order they were registered, so they should be quick.
+    pub fn check<F>(mut self, name: &str, check: F) -> Health
+    where
+        F: Fn() -> Result<(), String> + Send + Sync + 'static
+    {
+        self.checks.push((name.to_string(), Box::new(check)));
+        self
+    }
+
+    /// Number of jobs waiting for a worker above which the pool counts as saturated.
+    /// Defaults to the number of workers.
+    pub fn max_queued(mut self, max_queued: usize) -> Health {
+        self.max_queued = Some(max_queued);
+        self
+    }
+
+    /// Considers the pool when probing readiness.
+    pub fn with_pool(mut self, pool: Arc<PoolStats>) -> Health {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// While draining, readiness fails so no new traffic is routed here,
+    /// but requests are still served.
+    pub fn set_draining(&self, draining: bool) {
+        self.draining.store(draining, Ordering::Relaxed);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Whether the pool has more jobs waiting than allowed.
+    pub fn is_saturated(&self) -> bool {
+        self.pool.as_ref().is_some_and(|pool| {
+            pool.get_queued() > self.max_queued.unwrap_or(pool.get_workers())
+        })
+    }
+
+    /// Runs all checks and returns whether the server is ready,
+    /// with the JSON body of the readiness probe.
+    pub fn readiness(&self) -> (bool, Value) {
+        let draining = self.is_draining();
+        let saturated = self.is_saturated();
+
+        let mut ready = !draining && !saturated;
+        let mut checks = BTreeMap::new();
+        for (name, check) in &self.checks {
+            let status = match check() {
+                Ok(()) => "ok".to_string(),
+                Err(reason) => {
+                    ready = false;
+                    reason
+                }
+            };
+            checks.insert(name.clone(), Value::from(status));
+        }
+
+        let mut body = BTreeMap::new();
+        body.insert("status".to_string(), Value::from(if ready { "ready" } else { "not ready" }));
+        body.insert("draining".to_string(), Value::from(draining));
+        body.insert("saturated".to_string(), Value::from(saturated));
+        body.insert("checks".to_string(), Value::from(checks));
+        (ready, Value::from(body))
+    }
+
+    /// Answers the request if it is a probe, in which case true is returned
+    /// and the request must not be processed further.
+    pub(crate) fn handle<T: Read + Write>(&self, request: &mut RequestBackend<T>) -> bool {
+        let path = request.get_path();
+        let is_liveness = *path == self.liveness_path;
+        if !is_liveness && *path != self.readiness_path {
+            return false
+        }
+
+        let response = if !matches!(request.get_method().as_str(), "GET" | "HEAD") {
+            Response::builder()
+                .status(ResponseCode::get_405())
+                .header("Allow", "GET, HEAD")
+                .build()
+        } else if is_liveness {
+            let mut body = BTreeMap::new();
+            body.insert("status".to_string(), Value::from("ok"));
+            Response::json(&Value::from(body))
+        } else {
+            let (ready, body) = self.readiness();
+            let status = if ready { ResponseCode::get_200() } else { ResponseCode::get_503() };
+            Response::builder()
+                .status(status)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .build()
+        };
+
+        // Probes must never be cached
+        request.add_response_header(Header::new("Cache-Control", "no-store"));
+        let _ = request.respond(&response);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,109 @@
+use std::{io::Cursor, sync::{atomic::{AtomicBool, Ordering}, mpsc, Arc}, thread, time::Duration};
+
+use crate::json::Value;
+use crate::web::health::Health;
+use crate::web::request::RequestBackend;
+use crate::worker_pool::Pool;
+
+type MockStream = Cursor<Vec<u8>>;
+
+/// Runs [Health] handle on a request, returning whether it was
+/// answered and what was written to the client.
+fn handled(health: &Health, request_str: &str) -> (bool, String) {
+    let stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let mut req = RequestBackend::build(stream).unwrap();
+    let answered = health.handle(&mut req);
+
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    (answered, written)
+}
+
+/// Parses the JSON body of a response.
+fn body(written: &str) -> Value {
+    Value::parse(written.split_once("\r\n\r\n").unwrap().1).unwrap()
+}
+
+#[test]
+/// Test the liveness probe and requests that are not probes.
+fn liveness() {
+    let health = Health::new();
+
+    let (answered, written) = handled(&health, "GET /healthz HTTP/1.1\r\n\r\n");
+    assert!(answered);
+    assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(written.contains("\r\nCache-Control: no-store\r\n"));
+    assert!(body(&written).get("status").and_then(Value::as_str) == Some("ok"));
+
+    let (answered, written) = handled(&health, "POST /healthz HTTP/1.1\r\n\r\n");
+    assert!(answered);
+    assert!(written.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    assert!(written.contains("\r\nAllow: GET, HEAD\r\n"));
+
+    let (answered, written) = handled(&health, "GET /other HTTP/1.1\r\n\r\n");
+    assert!(!answered);
+    assert!(written.is_empty());
+
+    let health = Health::new().liveness_path("/live");
+    assert!(handled(&health, "GET /live HTTP/1.1\r\n\r\n").0);
+    assert!(!handled(&health, "GET /healthz HTTP/1.1\r\n\r\n").0);
+}
+
+#[test]
+/// Test that readiness follows checks and draining.
+fn readiness() {
+    let database_up = Arc::new(AtomicBool::new(true));
+    let flag = database_up.clone();
+    let health = Health::new()
+        .check("database", move || {
+            if flag.load(Ordering::Relaxed) { Ok(()) } else { Err("connection refused".to_string()) }
+        });
+
+    let (_, written) = handled(&health, "GET /readyz HTTP/1.1\r\n\r\n");
+    assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+    let ready = body(&written);
+    assert!(ready.get("status").and_then(Value::as_str) == Some("ready"));
+    assert!(ready.get("checks").and_then(|c| c.get("database")).and_then(Value::as_str) == Some("ok"));
+
+    database_up.store(false, Ordering::Relaxed);
+    let (_, written) = handled(&health, "GET /readyz HTTP/1.1\r\n\r\n");
+    assert!(written.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    let not_ready = body(&written);
+    assert!(not_ready.get("status").and_then(Value::as_str) == Some("not ready"));
+    assert!(not_ready.get("checks").and_then(|c| c.get("database")).and_then(Value::as_str) == Some("connection refused"));
+
+    database_up.store(true, Ordering::Relaxed);
+    health.set_draining(true);
+    let (_, written) = handled(&health, "GET /readyz HTTP/1.1\r\n\r\n");
+    assert!(written.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    assert!(body(&written).get("draining") == Some(&Value::Bool(true)));
+
+    // Liveness is not affected
+    let (_, written) = handled(&health, "GET /healthz HTTP/1.1\r\n\r\n");
+    assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[test]
+/// Test that a saturated pool is not ready.
+fn saturation() {
+    let pool = Pool::new(1);
+    let health = Health::new().max_queued(1).with_pool(pool.get_stats());
+    assert!(!health.is_saturated());
+
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || release_rx.recv().unwrap());
+    pool.execute(|| {});
+    pool.execute(|| {});
+
+    let mut saturated = false;
+    for _ in 0..100 {
+        saturated = health.is_saturated();
+        if saturated {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(saturated);
+    assert!(!health.readiness().0);
+
+    release_tx.send(()).unwrap();
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{Read, Write},
+    sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc, Mutex},
+    time::Duration
+};
+
+use crate::web::request::RequestBackend;
+use crate::web::response::{Response, ResponseCode};
+use crate::worker_pool::PoolStats;
+
+/// Upper bounds in seconds of the latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Route label of requests that matched no route, so clients
+/// probing random paths can't create unbounded label values.
+const UNMATCHED: &str = "unmatched";
+
+/// Methods that get their own label, any other method is labelled [OTHER_METHOD].
+const METHODS: [&str; 9] = ["GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH"];
+
+/// Method label of requests with a method outside of [METHODS], since
+/// the method comes from the request line as sent by the client.
+const OTHER_METHOD: &str = "other";
+
+/// Cumulative latency histogram of one route and method.
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+/// Counters and histograms of a server, exposed in the Prometheus text
+/// exposition format by [render](Metrics::render).
+/// Enabled with [PooledServer::with_metrics](super::PooledServer::with_metrics),
+/// which also registers the route that serves them.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    /// Keyed by route, method and status.
+    requests: Mutex<BTreeMap<(String, String, String), u64>>,
+    /// Keyed by route and method.
+    latency: Mutex<BTreeMap<(String, String), Histogram>>,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    open_connections: AtomicUsize,
+    pool: Option<Arc<PoolStats>>
+}
+
+/// Decrements open connections when the connection is done, even on panic.
+pub(crate) struct ConnectionGuard<'a>(&'a Metrics);
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.open_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /// Adds the queue depth and busy workers of a [Pool](crate::worker_pool::Pool).
+    pub fn with_pool(mut self, pool: Arc<PoolStats>) -> Metrics {
+        self.pool = Some(pool);
+        self
+    }
+
+    pub fn get_open_connections(&self) -> usize {
+        self.open_connections.load(Ordering::Relaxed)
+    }
+
+    /// Counts a connection as open until the guard is dropped.
+    pub(crate) fn connection(&self) -> ConnectionGuard<'_> {
+        self.open_connections.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self)
+    }
+
+    /// Records a served request.
+    ///
+    /// # Parameters
+    /// route - the registered route that matched, None if no route did.
+    /// duration - time from accepting the connection to the end of the handler.
+    pub(crate) fn observe<T: Read + Write>(&self, request: &RequestBackend<T>, route: Option<&str>, duration: Duration) {
+        let route = route.unwrap_or(UNMATCHED).to_string();
+        let method = match METHODS.iter().find(|method| **method == request.get_method()) {
+            Some(method) => method.to_string(),
+            None => OTHER_METHOD.to_string()
+        };
+        let status = request.get_response_code()
+            .map(|code| code.get_code().to_string())
+            .unwrap_or("none".to_string());
+
+        self.bytes_received.fetch_add(request.get_bytes_received(), Ordering::Relaxed);
+        self.bytes_sent.fetch_add(request.get_bytes_sent(), Ordering::Relaxed);
+        *self.requests.lock().unwrap_or_else(|p| p.into_inner())
+            .entry((route.clone(), method.clone(), status))
+            .or_default() += 1;
+        self.latency.lock().unwrap_or_else(|p| p.into_inner())
+            .entry((route, method))
+            .or_default()
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Records a request rejected before it could be read, e.g. with 400 or 408.
+    pub(crate) fn observe_rejected(&self, code: &ResponseCode) {
+        *self.requests.lock().unwrap_or_else(|p| p.into_inner())
+            .entry((UNMATCHED.to_string(), "unknown".to_string(), code.get_code().to_string()))
+            .or_default() += 1;
+    }
+
+    /// Formats all metrics in the Prometheus text exposition format (version 0.0.4).
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        header(&mut out, "rns_http_requests_total", "counter", "Requests served, by route, method and status.");
+        for ((route, method, status), count) in self.requests.lock().unwrap_or_else(|p| p.into_inner()).iter() {
+            let _ = writeln!(
+                out,
+                "rns_http_requests_total{{route=\"{}\",method=\"{}\",status=\"{}\"}} {count}",
+                escape(route), escape(method), escape(status)
+            );
+        }
+
+        header(&mut out, "rns_http_request_duration_seconds", "histogram", "Time to serve a request, by route and method.");
+        for ((route, method), histogram) in self.latency.lock().unwrap_or_else(|p| p.into_inner()).iter() {
+            let labels = format!("route=\"{}\",method=\"{}\"", escape(route), escape(method));
+            for (bucket, bound) in histogram.buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(out, "rns_http_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {bucket}");
+            }
+            let _ = writeln!(out, "rns_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}", histogram.count);
+            let _ = writeln!(out, "rns_http_request_duration_seconds_sum{{{labels}}} {}", histogram.sum);
+            let _ = writeln!(out, "rns_http_request_duration_seconds_count{{{labels}}} {}", histogram.count);
+        }
+
+        let scalars = [
+            ("rns_http_request_bytes_total", "counter", "Request body bytes received.", self.bytes_received.load(Ordering::Relaxed)),
+            ("rns_http_response_bytes_total", "counter", "Response body bytes sent.", self.bytes_sent.load(Ordering::Relaxed)),
+            ("rns_http_open_connections", "gauge", "Connections being served.", self.get_open_connections() as u64)
+        ];
+        let pool = self.pool.iter().flat_map(|pool| [
+            ("rns_pool_workers", "gauge", "Worker threads of the pool.", pool.get_workers() as u64),
+            ("rns_pool_queue_depth", "gauge", "Jobs waiting for a worker.", pool.get_queued() as u64),
+            ("rns_pool_busy_workers", "gauge", "Workers running a job.", pool.get_busy() as u64),
+            ("rns_pool_job_panics_total", "counter", "Jobs that panicked.", pool.get_panicked() as u64)
+        ]);
+        for (name, kind, help, value) in scalars.into_iter().chain(pool) {
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{name} {value}");
+        }
+
+        out
+    }
+
+    /// The response of the metrics route.
+    pub fn response(&self) -> Response {
+        Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+            .body(self.render().into_bytes())
+            .build()
+    }
+}
+
+/// Private helper for the HELP and TYPE lines of a metric.
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Private helper to escape a label value.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests;
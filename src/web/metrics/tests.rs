@@ -0,0 +1,84 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use crate::web::metrics::Metrics;
+use crate::web::request::RequestBackend;
+use crate::web::response::{Response, ResponseCode};
+use crate::worker_pool::Pool;
+
+type MockStream = Cursor<Vec<u8>>;
+
+/// A request answered with "hello".
+fn answered(request_str: &str) -> RequestBackend<MockStream> {
+    let stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let mut req = RequestBackend::build(stream).unwrap();
+    req.respond(&Response::text("hello")).unwrap();
+    req
+}
+
+#[test]
+/// Test request counters, latency histogram and byte counters.
+fn requests() {
+    let metrics = Metrics::new();
+    let req = answered("POST /a HTTP/1.1\r\nContent-Length: 3\r\n\r\nabc");
+
+    metrics.observe(&req, Some("/a"), Duration::from_millis(20));
+    metrics.observe(&req, Some("/a"), Duration::from_secs(20));
+    metrics.observe(&answered("GET /x?\" HTTP/1.1\r\n\r\n"), None, Duration::ZERO);
+    metrics.observe(&answered("M1 /x HTTP/1.1\r\n\r\n"), None, Duration::ZERO);
+    metrics.observe(&answered("M2 /x HTTP/1.1\r\n\r\n"), None, Duration::ZERO);
+    metrics.observe_rejected(&ResponseCode::get_408());
+
+    let text = metrics.render();
+    assert!(text.contains("# TYPE rns_http_requests_total counter\n"));
+    assert!(text.contains("\nrns_http_requests_total{route=\"/a\",method=\"POST\",status=\"200\"} 2\n"));
+    assert!(text.contains("\nrns_http_requests_total{route=\"unmatched\",method=\"GET\",status=\"200\"} 1\n"));
+    assert!(text.contains("\nrns_http_requests_total{route=\"unmatched\",method=\"unknown\",status=\"408\"} 1\n"));
+    assert!(text.contains("\nrns_http_requests_total{route=\"unmatched\",method=\"other\",status=\"200\"} 2\n"));
+    assert!(!text.contains("M1"), "non-standard methods must not become labels");
+
+    assert!(text.contains("# TYPE rns_http_request_duration_seconds histogram\n"));
+    assert!(text.contains("\nrns_http_request_duration_seconds_bucket{route=\"/a\",method=\"POST\",le=\"0.01\"} 0\n"));
+    assert!(text.contains("\nrns_http_request_duration_seconds_bucket{route=\"/a\",method=\"POST\",le=\"0.025\"} 1\n"));
+    assert!(text.contains("\nrns_http_request_duration_seconds_bucket{route=\"/a\",method=\"POST\",le=\"10\"} 1\n"));
+    assert!(text.contains("\nrns_http_request_duration_seconds_bucket{route=\"/a\",method=\"POST\",le=\"+Inf\"} 2\n"));
+    assert!(text.contains("\nrns_http_request_duration_seconds_sum{route=\"/a\",method=\"POST\"} 20.02\n"));
+    assert!(text.contains("\nrns_http_request_duration_seconds_count{route=\"/a\",method=\"POST\"} 2\n"));
+
+    assert!(text.contains("\nrns_http_request_bytes_total 6\n"));
+    assert!(text.contains("\nrns_http_response_bytes_total 25\n"));
+    assert!(!text.contains("rns_pool_"));
+}
+
+#[test]
+/// Test the connection and pool gauges.
+fn gauges() {
+    let pool = Pool::new(3);
+    let metrics = Metrics::new().with_pool(pool.get_stats());
+
+    let first = metrics.connection();
+    let second = metrics.connection();
+    assert!(metrics.get_open_connections() == 2);
+    drop(first);
+
+    let text = metrics.render();
+    assert!(text.contains("\nrns_http_open_connections 1\n"));
+    assert!(text.contains("# TYPE rns_pool_workers gauge\nrns_pool_workers 3\n"));
+    assert!(text.contains("\nrns_pool_queue_depth 0\n"));
+    assert!(text.contains("\nrns_pool_busy_workers 0\n"));
+    assert!(text.contains("# TYPE rns_pool_job_panics_total counter\nrns_pool_job_panics_total 0\n"));
+
+    drop(second);
+    assert!(metrics.get_open_connections() == 0);
+}
+
+#[test]
+/// Test that the response is served as Prometheus text.
+fn response() {
+    let metrics = Arc::new(Metrics::new());
+    let response = metrics.response();
+
+    assert!(response.get_code().get_code() == 200);
+    assert!(response.get_header("Content-Type").is_some_and(
+        |header| header.get_value() == "text/plain; version=0.0.4; charset=utf-8"
+    ));
+}
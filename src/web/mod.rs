@@ -1,13 +1,31 @@
 use std::collections::HashMap;
-use std::net::TcpStream;
+use std::io::Error;
+use std::net::{IpAddr, Shutdown, TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::web::response::{ResponseCode};
-use crate::web::request::Request;
-use crate::worker_pool::Pool;
+use crate::log;
+use crate::web::access_log::{AccessLog, Entry};
+use crate::web::cors::CorsConfig;
+use crate::web::health::Health;
+use crate::web::metrics::Metrics;
+use crate::web::response::{Header, Response, ResponseCode};
+use crate::web::request::{Request, Timeouts, MAX_BODY_SIZE};
+use crate::web::session::SessionConfig;
+use crate::web::uri::normalize_path;
+use crate::worker_pool::{panic_message, Pool};
 
+pub mod access_log;
+pub mod cookie;
+pub mod cors;
+pub mod health;
+pub mod metrics;
+pub mod multipart;
 pub mod request;
 pub mod response;
+pub mod session;
+pub mod uri;
 
 /// Disambiguation for [RouteMap]
 type Uri = String;
@@ -15,11 +33,14 @@ type Uri = String;
 type Method = String;
 /// A shared (across threads) pointer to the fn or closure 
 /// that gets executed during HTTP response. Used in [RouteMap].
-type Action = Arc<dyn Fn(Request)>;
+type Action = Arc<dyn Fn(&mut Request) + Send + Sync>;
 
 /// Public api for registering actions to method + URI combinations (called routes).
 /// After all routes are registered, the RouteMap is to be consumed by a [Server].
 /// insert_* methods follow the [HashMap] rules for overwriting keys.
+/// URIs are stored normalised by [normalize_path], so "/a b" and "/a%20b"
+/// name the same route. Lookups are expected to use a normalised path, such
+/// as [RequestBackend::get_path](request::RequestBackend::get_path).
 /// [insert_route_methods] is a shorthand for registering the same 
 /// action to multiple methods under the same URI.
 /// # Example
@@ -31,7 +52,7 @@ type Action = Arc<dyn Fn(Request)>;
 /// 
 /// let mut route_map = RouteMap::new();
 /// 
-/// fn dummy_function(request: Request) {}
+/// fn dummy_function(request: &mut Request) {}
 /// 
 /// let fn_ptr = Arc::new(dummy_function);
 /// 
@@ -56,6 +77,12 @@ pub struct RouteMap {
     >
 }
 
+impl Default for RouteMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl RouteMap {
     pub fn new() -> RouteMap {
         RouteMap { map: HashMap::new() }
@@ -87,6 +114,9 @@ impl RouteMap {
 
     /// Returns action pointer or [ResponseCode] of [404, 405]
     /// that can be used for a meaningful http response.
+    /// HEAD falls back to the GET action (the body is dropped when responding)
+    /// and OPTIONS without a route gets an action answering with the Allow header.
+    /// The methods for the Allow header of a 405 are given by [get_methods].
     pub fn get_action(&self, uri: &String, method: &String) -> Result<Action, ResponseCode> {
         // Check for uri
         match self.map.get(uri) {
@@ -97,6 +127,21 @@ impl RouteMap {
                         // Ok
                         Result::Ok(action.clone())
                     }
+                    None if method == "HEAD" && method_map.contains_key("GET") => {
+                        Result::Ok(method_map["GET"].clone())
+                    }
+                    None if method == "OPTIONS" => {
+                        let allow = self.get_methods(uri).unwrap_or_default().join(", ");
+                        Result::Ok(
+                            Arc::new(move |request: &mut Request| {
+                                let response = Response::builder()
+                                    .status(ResponseCode::get_204())
+                                    .header("Allow", &allow)
+                                    .build();
+                                let _ = request.respond(&response);
+                            })
+                        )
+                    }
                     None => {
                         // uri exists but method is not registered => Method Not Allowed
                         Result::Err(
@@ -114,9 +159,27 @@ impl RouteMap {
         }
     }
 
-    /// Private helper method to get or create method map
+    /// Returns the methods allowed for the uri, sorted, or None if the uri
+    /// has no routes. Includes HEAD when GET is registered, and OPTIONS.
+    pub fn get_methods(&self, uri: &String) -> Option<Vec<Method>> {
+        let method_map = self.map.get(uri)?;
+        let mut methods: Vec<Method> = method_map.keys().cloned().collect();
+        for implied in ["HEAD", "OPTIONS"] {
+            let applies = implied == "OPTIONS" || method_map.contains_key("GET");
+            if applies && !method_map.contains_key(implied) {
+                methods.push(implied.to_string());
+            }
+        }
+        methods.sort();
+        Some(methods)
+    }
+
+    /// Private helper method to get or create method map.
+    /// URIs that can not be normalised (e.g. "*") are stored as is.
     fn get_method_map(&mut self, uri: String) -> &mut HashMap<Method, Action> {
-        if let None = self.map.get(&uri) {
+        let uri = normalize_path(&uri).unwrap_or(uri);
+
+        if !self.map.contains_key(&uri) {
             let method_map = HashMap::new();
             self.map.insert(uri.clone(), method_map);
         }
@@ -126,78 +189,373 @@ impl RouteMap {
     }
 }
 
+/// Everything a worker needs to serve a request.
+/// Shared between workers and read only once the server runs.
+struct Settings {
+    route_map: RouteMap,
+    timeouts: Timeouts,
+    max_body: u64,
+    sessions: Option<Arc<SessionConfig>>,
+    cors: Option<CorsConfig>,
+    access_log: Option<AccessLog>,
+    metrics: Option<Arc<Metrics>>,
+    health: Option<Arc<Health>>
+}
+
+impl Settings {
+    /// Default timeouts and body limit, no middleware.
+    fn new(route_map: RouteMap) -> Settings {
+        Settings {
+            route_map,
+            timeouts: Timeouts::default(),
+            max_body: MAX_BODY_SIZE,
+            sessions: None,
+            cors: None,
+            access_log: None,
+            metrics: None,
+            health: None
+        }
+    }
+}
+
 /// Implements the non-public interface of a webserver.
 /// [serve_request] invokes the request processing chain
 /// that goes in order of:
 /// [authenticate] -> [throttle] -> [dispatch] -> closure().
-/// The [serve_request] method also uses a shared read only
-/// [RouteMap] to look up the closure at the end of the chain.
+/// The [serve_request] method also uses shared read only
+/// [Settings] to look up the closure at the end of the chain.
 trait ServerBackend {
     fn authenticate(request: &Request) -> Result<(), ResponseCode>;
     fn throttle(request: &Request) -> Result<(), ResponseCode>;
-    fn dispatch(request: &Request) -> Result<impl Fn(Request), ResponseCode>;
+    fn dispatch(request: &Request, route_map: &RouteMap) -> Result<Action, ResponseCode>;
 
-    fn get_route_map(&mut self) -> Arc<RouteMap>;
-    fn serve_request(socket: TcpStream, route_map: Arc<RouteMap>);
+    fn get_address(&self) -> &str;
+    fn get_settings(&self) -> Arc<Settings>;
+    fn get_worker_pool(&self) -> &Pool;
+    fn serve_request(socket: TcpStream, settings: Arc<Settings>);
+    fn serve_overloaded(socket: TcpStream, settings: Arc<Settings>);
 }
 
 /// Implements the public interface of a webserver.
 /// [run] method will start a blocking infinite loop
 /// that awaits connections and handles them.
+/// Only returns on failure to bind the address.
 pub trait Server {
-    fn run(&self);
+    fn run(&self) -> Result<(), Error>;
 }
 
-impl<T: ServerBackend> Server for T {
-    fn run(&self) {
-        
+impl<T: ServerBackend + 'static> Server for T {
+    fn run(&self) -> Result<(), Error> {
+        let listener = TcpListener::bind(self.get_address())?;
+
+        for socket in listener.incoming() {
+            match socket {
+                Ok(socket) => {
+                    let settings = self.get_settings();
+                    let pool = self.get_worker_pool();
+
+                    // A full queue of a bounded pool hands the job back, keep a
+                    // handle to the client to tell it to come back later
+                    let overflow = match pool.get_capacity() {
+                        Some(_) => socket.try_clone().ok(),
+                        None => None
+                    };
+                    let rejected = pool.try_execute({
+                        let settings = settings.clone();
+                        move || T::serve_request(socket, settings)
+                    });
+                    if rejected.is_err()
+                        && let Some(overflow) = overflow {
+                        T::serve_overloaded(overflow, settings);
+                    }
+                }
+                Err(err) => {
+                    log::warn(module_path!(), "could not accept a connection", &[("error", &err)]);
+                }
+            }
+        }
+
+        Result::Ok(())
     }
 }
 
 /// Server + ServerBackend with a pool of workers to
 /// process connections concurrently. 
+/// 
+/// # Example
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// 
+/// use rns::web::{PooledServer, RouteMap, Server};
+/// use rns::web::request::Timeouts;
+/// use rns::web::session::{MemoryStore, SessionConfig};
+/// 
+/// let store = Arc::new(MemoryStore::new(Duration::from_secs(30 * 60)));
+/// 
+/// let server = PooledServer::new("127.0.0.1:8080".to_string(), RouteMap::new(), 4)
+///     .with_timeouts(Timeouts::default().header(Duration::from_secs(5)))
+///     .with_sessions(SessionConfig::new(store, b"a secret key of at least 32 bytes!"));
+/// 
+/// server.run().unwrap();
+/// ```
 pub struct PooledServer {
-    routes: RouteMap,
+    address: String,
+    settings: Arc<Settings>,
     worker_pool: Pool
 }
 
+impl PooledServer {
+    /// Creates a server that is yet to be [run](Server::run).
+    /// 
+    /// # Parameters
+    /// address - an address to listen on, e.g. "127.0.0.1:8080".
+    /// routes - a complete [RouteMap] to be shared between workers.
+    /// n_workers - passed to [Pool::new].
+    /// 
+    /// # Panics
+    /// Same as [Pool::new].
+    pub fn new(address: String, routes: RouteMap, n_workers: usize) -> PooledServer {
+        PooledServer::from_pool(address, routes, Pool::new(n_workers))
+    }
+
+    /// Same as [new], but connections are served by the given [Pool].
+    /// With a [bounded](Pool::bounded) pool, connections that find the
+    /// queue full are answered with 503 right away instead of waiting.
+    pub fn from_pool(address: String, routes: RouteMap, pool: Pool) -> PooledServer {
+        PooledServer {
+            address,
+            settings: Arc::new(Settings::new(routes)),
+            worker_pool: pool
+        }
+    }
+
+    /// Replaces the default [Timeouts] applied to every connection.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> PooledServer {
+        self.settings_mut().timeouts = timeouts;
+        self
+    }
+
+    /// Replaces the default [MAX_BODY_SIZE] of request bodies in bytes.
+    /// Larger bodies are answered with 413 before they are read. Multipart
+    /// bodies are streamed instead, bounded by [MultipartLimits](multipart::MultipartLimits).
+    pub fn with_max_body(mut self, size: u64) -> PooledServer {
+        self.settings_mut().max_body = size;
+        self
+    }
+
+    /// Enables the session middleware: every request gets a [Session](session::Session),
+    /// which is saved after the handler returns. New sessions are saved only
+    /// if the handler wrote to them before responding.
+    pub fn with_sessions(mut self, config: SessionConfig) -> PooledServer {
+        self.settings_mut().sessions = Some(Arc::new(config));
+        self
+    }
+
+    /// Enables the CORS middleware: preflight requests are answered without
+    /// a registered OPTIONS route, other requests get CORS headers.
+    pub fn with_cors(mut self, config: CorsConfig) -> PooledServer {
+        self.settings_mut().cors = Some(config);
+        self
+    }
+
+    /// Writes a line to the [AccessLog] for every request, including
+    /// the ones rejected before reaching a handler.
+    pub fn with_access_log(mut self, access_log: AccessLog) -> PooledServer {
+        self.settings_mut().access_log = Some(access_log);
+        self
+    }
+
+    /// Collects [Metrics] of requests, connections and the worker pool,
+    /// and serves them on GET [path] (usually "/metrics").
+    pub fn with_metrics(mut self, path: &str) -> PooledServer {
+        let metrics = Arc::new(Metrics::new().with_pool(self.worker_pool.get_stats()));
+        let shared = metrics.clone();
+
+        let settings = self.settings_mut();
+        settings.route_map.insert_route(
+            path.to_string(),
+            "GET".to_string(),
+            Arc::new(move |request: &mut Request| {
+                let _ = request.respond(&shared.response());
+            })
+        );
+        settings.metrics = Some(metrics);
+        self
+    }
+
+    /// Answers liveness and readiness probes, see [Health].
+    /// The pool of the server counts towards readiness.
+    pub fn with_health(mut self, health: Health) -> PooledServer {
+        let health = health.with_pool(self.worker_pool.get_stats());
+        self.settings_mut().health = Some(Arc::new(health));
+        self
+    }
+
+    /// The [Health] given to [with_health], e.g. to start draining.
+    pub fn get_health(&self) -> Option<Arc<Health>> {
+        self.settings.health.clone()
+    }
+
+    /// Private helper to configure [Settings]. They are only shared
+    /// with workers by [run](Server::run), which takes &self.
+    fn settings_mut(&mut self) -> &mut Settings {
+        Arc::get_mut(&mut self.settings).expect("settings are not shared before run")
+    }
+}
+
 impl ServerBackend for PooledServer {
-    fn authenticate(request: &Request) -> Result<(), ResponseCode> {
+    fn authenticate(_request: &Request) -> Result<(), ResponseCode> {
         Ok(())
     }
 
-    fn throttle(request: &Request) -> Result<(), ResponseCode> {
+    fn throttle(_request: &Request) -> Result<(), ResponseCode> {
         Ok(())
     }
 
-    fn dispatch(request: &Request) -> Result<impl Fn(Request), ResponseCode> {
-        Ok(|request| {})
+    fn dispatch(request: &Request, route_map: &RouteMap) -> Result<Action, ResponseCode> {
+        route_map.get_action(request.get_path(), request.get_method())
     }
 
-    fn get_route_map(&mut self) -> Arc<RouteMap> {
-        Arc::new(RouteMap::new())
+    fn get_address(&self) -> &str {
+        &self.address
     }
 
-    fn serve_request(socket: TcpStream, route_map: Arc<RouteMap>) {
-        let request = Request::build(socket).unwrap();
+    fn get_settings(&self) -> Arc<Settings> {
+        self.settings.clone()
+    }
+
+    fn get_worker_pool(&self) -> &Pool {
+        &self.worker_pool
+    }
+
+    fn serve_request(socket: TcpStream, settings: Arc<Settings>) {
+        let started = Instant::now();
+        let time = SystemTime::now();
+        let client = socket.peer_addr().ok().map(|address| address.ip());
+        let _connection = settings.metrics.as_ref().map(|metrics| metrics.connection());
+
+        // On failure the client was already answered by build_served().
+        let mut request = match Request::build_served(socket, &settings.timeouts, settings.max_body) {
+            Ok(request) => request,
+            Err(rejection) => {
+                Self::record_rejected(
+                    &settings, client, time, rejection.get_request_line(), rejection.get_code(), started.elapsed()
+                );
+                return
+            }
+        };
 
-        if let Err(status) = <PooledServer as ServerBackend>::authenticate(&request) {
+        Self::handle(&mut request, &settings);
 
+        let duration = started.elapsed();
+        if let Some(access_log) = &settings.access_log {
+            access_log.log(&Entry::from_request(&request, client, time, duration));
         }
+        if let Some(metrics) = &settings.metrics {
+            let path = request.get_path();
+            let route = settings.route_map.get_methods(path).map(|_| path.as_str());
+            metrics.observe(&request, route, duration);
+        }
+    }
+
+    fn serve_overloaded(mut socket: TcpStream, settings: Arc<Settings>) {
+        let time = SystemTime::now();
+        let client = socket.peer_addr().ok().map(|address| address.ip());
+        let code = ResponseCode::get_503();
 
-        if let Err(status) = <PooledServer as ServerBackend>::throttle(&request) {
-            
+        // Runs on the accepting thread, which must not wait for a slow client
+        let _ = socket.set_write_timeout(Some(settings.timeouts.get_write()));
+        let response = Response::builder()
+            .status(code.clone())
+            .header("Retry-After", "1")
+            .header("Connection", "close")
+            .build();
+        let _ = response.respond(&mut socket);
+        let _ = socket.shutdown(Shutdown::Write);
+
+        Self::record_rejected(&settings, client, time, None, &code, Duration::ZERO);
+    }
+}
+
+impl PooledServer {
+    /// Private helper to log a request that never reached a handler.
+    fn record_rejected(
+        settings: &Settings,
+        client: Option<IpAddr>,
+        time: SystemTime,
+        request_line: Option<&str>,
+        code: &ResponseCode,
+        duration: Duration
+    ) {
+        if let Some(access_log) = &settings.access_log {
+            access_log.log(&Entry::rejected(client, time, request_line, code, duration));
+        }
+        if let Some(metrics) = &settings.metrics {
+            metrics.observe_rejected(code);
+        }
+    }
+
+    /// Private helper that runs the middleware around [process].
+    fn handle(request: &mut Request, settings: &Settings) {
+        // Probes skip authentication and throttling
+        if let Some(health) = &settings.health
+            && health.handle(request) {
+            return;
         }
 
-        match <PooledServer as ServerBackend>::dispatch(&request) {
-            Ok(closure) => {
+        if let Some(cors) = &settings.cors
+            && cors.handle(request, &settings.route_map) {
+            return;
+        }
+
+        if let Some(sessions) = &settings.sessions {
+            let cookie_value = request.get_cookie(sessions.get_cookie_name());
+            request.set_session(sessions.start(cookie_value.as_deref()));
+        }
 
+        Self::process(request, &settings.route_map);
+
+        if let Some(session) = request.take_session()
+            && let Err(err) = session.persist() {
+            log::error(module_path!(), "could not save a session", &[("error", &err)]);
+        }
+    }
+
+    /// Private helper that runs the chain from [authenticate] to the closure.
+    fn process(request: &mut Request, route_map: &RouteMap) {
+        if let Err(status) = <PooledServer as ServerBackend>::authenticate(request) {
+            let _ = request.respond_code(status);
+            return;
+        }
+
+        if let Err(status) = <PooledServer as ServerBackend>::throttle(request) {
+            let _ = request.respond_code(status);
+            return;
+        }
+
+        match <PooledServer as ServerBackend>::dispatch(request, route_map) {
+            Ok(closure) => {
+                // A buggy handler costs only its own request
+                if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| closure(request))) {
+                    log::error(
+                        module_path!(),
+                        "handler panicked",
+                        &[("path", request.get_path()), ("panic", &panic_message(payload.as_ref()))]
+                    );
+                    if request.get_response_code().is_none() {
+                        let _ = request.respond_code(ResponseCode::get_500());
+                    }
+                }
             }
             Err(status) => {
-
+                if status == ResponseCode::get_405()
+                    && let Some(methods) = route_map.get_methods(request.get_path()) {
+                    request.add_response_header(Header::new("Allow", &methods.join(", ")));
+                }
+                let _ = request.respond_code(status);
             }
         }
-
     }
 }
 
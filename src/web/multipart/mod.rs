@@ -0,0 +1,545 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{Error, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use crate::web::response::{Header, ResponseCode, WebResult};
+use crate::web::uri::percent_decode;
+
+/// Size of a read from the body source.
+const READ_SIZE: usize = 8 * 1024;
+/// Limit on the size of headers of a single part.
+const MAX_PART_HEADERS: usize = 8 * 1024;
+
+/// Limits applied while parsing "multipart/form-data".
+/// [part_size] and [total_size] are answered with 413 (HTTP 413) when exceeded.
+/// A part that grows past [memory_threshold] is moved to a [TempFile]
+/// in [temp_dir], so large uploads are not held in memory.
+/// 
+/// # Example
+/// ```
+/// use rns::web::multipart::MultipartLimits;
+/// 
+/// let limits = MultipartLimits::default()
+///     .part_size(64 * 1024 * 1024)
+///     .memory_threshold(256 * 1024);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultipartLimits {
+    part_size: usize,
+    total_size: usize,
+    memory_threshold: usize,
+    temp_dir: PathBuf
+}
+
+impl MultipartLimits {
+    pub fn part_size(mut self, size: usize) -> MultipartLimits {
+        self.part_size = size;
+        self
+    }
+
+    pub fn total_size(mut self, size: usize) -> MultipartLimits {
+        self.total_size = size;
+        self
+    }
+
+    pub fn memory_threshold(mut self, size: usize) -> MultipartLimits {
+        self.memory_threshold = size;
+        self
+    }
+
+    pub fn temp_dir(mut self, dir: PathBuf) -> MultipartLimits {
+        self.temp_dir = dir;
+        self
+    }
+
+    pub const fn get_part_size(&self) -> usize {
+        self.part_size
+    }
+
+    pub const fn get_total_size(&self) -> usize {
+        self.total_size
+    }
+
+    pub const fn get_memory_threshold(&self) -> usize {
+        self.memory_threshold
+    }
+
+    pub fn get_temp_dir(&self) -> &Path {
+        &self.temp_dir
+    }
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        MultipartLimits {
+            part_size: 16 * 1024 * 1024,
+            total_size: 32 * 1024 * 1024,
+            memory_threshold: 1024 * 1024,
+            temp_dir: env::temp_dir()
+        }
+    }
+}
+
+/// A file that is removed when dropped, unless [persist]ed.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    size: u64,
+    keep: bool
+}
+
+impl TempFile {
+    /// Creates a new empty file with a unique name in [dir].
+    fn create(dir: &Path) -> Result<(TempFile, File), Error> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+        let name = format!(
+            "rns-upload-{}-{}-{nanos}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = dir.join(name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        // Temp dirs are often shared, uploads must not be readable by other users
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(&path)?;
+
+        Result::Ok((TempFile { path, size: 0, keep: false }, file))
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub const fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn open(&self) -> Result<File, Error> {
+        File::open(&self.path)
+    }
+
+    /// Moves the file to [to] and keeps it. Falls back to copying when
+    /// [to] is on another file system.
+    pub fn persist(mut self, to: &Path) -> Result<(), Error> {
+        if fs::rename(&self.path, to).is_err() {
+            fs::copy(&self.path, to)?;
+            let _ = fs::remove_file(&self.path);
+        }
+        self.keep = true;
+        Result::Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Content of a [Part], either in memory or spilled to a [TempFile].
+#[derive(Debug)]
+pub enum PartData {
+    Memory(Vec<u8>),
+    File(TempFile)
+}
+
+impl PartData {
+    /// Reads the content into memory, wherever it is stored.
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            PartData::Memory(bytes) => Result::Ok(bytes.clone()),
+            PartData::File(file) => fs::read(file.get_path())
+        }
+    }
+}
+
+/// One part of a "multipart/form-data" body.
+#[derive(Debug)]
+pub struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: Vec<Header>,
+    data: PartData
+}
+
+impl Part {
+    /// Name of the form field from Content-Disposition.
+    pub const fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Client side file name, present for file inputs.
+    pub fn get_filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn get_content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub const fn get_headers(&self) -> &Vec<Header> {
+        &self.headers
+    }
+
+    pub const fn get_data(&self) -> &PartData {
+        &self.data
+    }
+
+    pub fn into_data(self) -> PartData {
+        self.data
+    }
+
+    /// Returns the content of an in memory part as text.
+    /// None for parts in files or that are not UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        match &self.data {
+            PartData::Memory(bytes) => std::str::from_utf8(bytes).ok(),
+            PartData::File(_) => None
+        }
+    }
+}
+
+/// Splits a header value like `form-data; name="a"; filename="b.txt"` into
+/// the leading value and lower cased parameter names with unquoted values.
+pub(crate) fn parse_parameters(value: &str) -> (String, Vec<(String, String)>) {
+    let mut chars = value.chars().peekable();
+    let mut leading = String::new();
+    while let Some(c) = chars.next_if(|c| *c != ';') {
+        leading.push(c);
+    }
+
+    let mut parameters = Vec::new();
+    while chars.next().is_some() { // ;
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| *c != '=' && *c != ';') {
+            name.push(c);
+        }
+
+        let mut param_value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            while chars.next_if(|c| *c == ' ').is_some() {}
+            if chars.next_if_eq(&'"').is_some() {
+                // Quoted string with backslash escapes
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => param_value.extend(chars.next()),
+                        c => param_value.push(c)
+                    }
+                }
+                while chars.next_if(|c| *c != ';').is_some() {}
+            } else {
+                while let Some(c) = chars.next_if(|c| *c != ';') {
+                    param_value.push(c);
+                }
+                param_value = param_value.trim().to_string();
+            }
+        }
+
+        let name = name.trim().to_ascii_lowercase();
+        if !name.is_empty() {
+            parameters.push((name, param_value));
+        }
+    }
+
+    (leading.trim().to_string(), parameters)
+}
+
+/// Private buffered scanner over the body source that counts bytes
+/// against [MultipartLimits::total_size].
+struct Scanner<'a, R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    eof: bool,
+    total: usize,
+    limits: &'a MultipartLimits
+}
+
+impl<R: Read> Scanner<'_, R> {
+    /// Reads more of the source. Returns false on the end of the source.
+    fn fill(&mut self) -> WebResult<bool> {
+        if self.eof {
+            return Result::Ok(false)
+        }
+
+        let len = self.buf.len();
+        self.buf.resize(len + READ_SIZE, 0);
+        let read = match self.reader.read(&mut self.buf[len..]) {
+            Ok(read) => read,
+            Err(err) => {
+                self.buf.truncate(len);
+                return Result::Err(
+                    match err.kind() {
+                        ErrorKind::TimedOut | ErrorKind::WouldBlock => ResponseCode::get_408(),
+                        _ => ResponseCode::get_400()
+                    }
+                )
+            }
+        };
+        self.buf.truncate(len + read);
+
+        self.total += read;
+        if self.total > self.limits.get_total_size() {
+            return Result::Err(
+                ResponseCode::get_413()
+            )
+        }
+
+        self.eof = read == 0;
+        Result::Ok(!self.eof)
+    }
+
+    /// Makes sure at least [len] bytes are buffered, if the source has them.
+    fn ensure(&mut self, len: usize) -> WebResult<bool> {
+        while self.buf.len() < len {
+            if !self.fill()? {
+                return Result::Ok(false)
+            }
+        }
+        Result::Ok(true)
+    }
+
+    /// Passes everything before [delimiter] to [sink] and consumes the delimiter.
+    /// Bytes are passed on as soon as they can't be a start of the delimiter.
+    /// Returns [ResponseCode] of 400 (HTTP 400) if the source ends first.
+    fn read_until(
+        &mut self,
+        delimiter: &[u8],
+        sink: &mut dyn FnMut(&[u8]) -> WebResult<()>
+    ) -> WebResult<()> {
+        loop {
+            if let Some(end) = find(&self.buf, delimiter) {
+                sink(&self.buf[..end])?;
+                self.buf.drain(..end + delimiter.len());
+                return Result::Ok(())
+            }
+
+            let safe = self.buf.len().saturating_sub(delimiter.len() - 1);
+            sink(&self.buf[..safe])?;
+            self.buf.drain(..safe);
+
+            if !self.fill()? {
+                return Result::Err(
+                    ResponseCode::get_400()
+                )
+            }
+        }
+    }
+}
+
+/// Private helper to find the first occurrence of [needle].
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Private sink that keeps a part in memory until it crosses the threshold.
+struct PartSink<'a> {
+    data: PartData,
+    file: Option<File>,
+    size: usize,
+    limits: &'a MultipartLimits
+}
+
+impl PartSink<'_> {
+    fn write(&mut self, bytes: &[u8]) -> WebResult<()> {
+        self.size += bytes.len();
+        if self.size > self.limits.get_part_size() {
+            return Result::Err(
+                ResponseCode::get_413()
+            )
+        }
+
+        if let PartData::Memory(memory) = &self.data
+            && self.size > self.limits.get_memory_threshold() {
+            let (temp_file, mut file) = TempFile::create(self.limits.get_temp_dir())
+                .map_err(|_| ResponseCode::get_500())?;
+            file.write_all(memory).map_err(|_| ResponseCode::get_500())?;
+            self.data = PartData::File(temp_file);
+            self.file = Some(file);
+        }
+
+        match (&mut self.data, &mut self.file) {
+            (PartData::Memory(memory), _) => memory.extend_from_slice(bytes),
+            (PartData::File(temp_file), Some(file)) => {
+                file.write_all(bytes).map_err(|_| ResponseCode::get_500())?;
+                temp_file.size = self.size as u64;
+            }
+            (PartData::File(_), None) => unreachable!("file is opened with the temp file")
+        }
+
+        Result::Ok(())
+    }
+}
+
+/// Parses a "multipart/form-data" body read from [reader].
+/// The source is consumed as parts are parsed, so large parts go straight
+/// to temporary files.
+/// Returns [ResponseCode] of:
+/// 1. 400 (HTTP 400) on malformed input, including a part without a name;
+/// 2. 408 (HTTP 408) if reading the source timed out;
+/// 3. 413 (HTTP 413) if a part or the whole body exceeds [MultipartLimits];
+/// 4. 500 (HTTP 500) if a temporary file can't be written.
+/// 
+/// # Parameters
+/// reader - the body source.
+/// boundary - the boundary parameter of Content-Type.
+/// limits - see [MultipartLimits].
+pub fn parse<R: Read>(reader: R, boundary: &str, limits: &MultipartLimits) -> WebResult<Vec<Part>> {
+    // RFC 2046 limits boundaries to 70 characters
+    if boundary.is_empty() || boundary.len() > 70 {
+        return Result::Err(
+            ResponseCode::get_400()
+        )
+    }
+
+    let mut scanner = Scanner { reader, buf: Vec::new(), eof: false, total: 0, limits };
+    let dash_boundary = format!("--{boundary}");
+    let delimiter = format!("\r\n--{boundary}");
+
+    // Skip the preamble, the first boundary may start the body
+    scanner.ensure(dash_boundary.len())?;
+    if scanner.buf.starts_with(dash_boundary.as_bytes()) {
+        scanner.buf.drain(..dash_boundary.len());
+    } else {
+        scanner.read_until(delimiter.as_bytes(), &mut |_| Result::Ok(()))?;
+    }
+
+    let mut parts = Vec::new();
+    loop {
+        // "--" after a boundary closes the body, the epilogue is ignored
+        if !scanner.ensure(2)? {
+            return Result::Err(
+                ResponseCode::get_400()
+            )
+        }
+        if scanner.buf.starts_with(b"--") {
+            return Result::Ok(parts)
+        }
+
+        // Rest of the boundary line may only be transport padding
+        let mut padding = Vec::new();
+        scanner.read_until(b"\r\n", &mut |bytes| {
+            padding.extend_from_slice(bytes);
+            if padding.len() > MAX_PART_HEADERS {
+                return Result::Err(
+                    ResponseCode::get_400()
+                )
+            }
+            Result::Ok(())
+        })?;
+        if padding.iter().any(|b| *b != b' ' && *b != b'\t') {
+            return Result::Err(
+                ResponseCode::get_400()
+            )
+        }
+
+        let headers = read_part_headers(&mut scanner)?;
+        let mut sink = PartSink {
+            data: PartData::Memory(Vec::new()),
+            file: None,
+            size: 0,
+            limits
+        };
+        scanner.read_until(delimiter.as_bytes(), &mut |bytes| sink.write(bytes))?;
+
+        parts.push(build_part(headers, sink.data)?);
+    }
+}
+
+/// Private helper to read the header block of a part.
+fn read_part_headers<R: Read>(scanner: &mut Scanner<R>) -> WebResult<Vec<Header>> {
+    if !scanner.ensure(2)? {
+        return Result::Err(
+            ResponseCode::get_400()
+        )
+    }
+    // Part without headers
+    if scanner.buf.starts_with(b"\r\n") {
+        scanner.buf.drain(..2);
+        return Result::Ok(Vec::new())
+    }
+
+    let mut block = Vec::new();
+    scanner.read_until(b"\r\n\r\n", &mut |bytes| {
+        block.extend_from_slice(bytes);
+        if block.len() > MAX_PART_HEADERS {
+            return Result::Err(
+                ResponseCode::get_400()
+            )
+        }
+        Result::Ok(())
+    })?;
+
+    let block = match String::from_utf8(block) {
+        Ok(block) => block,
+        Err(_) => return Result::Err(
+            ResponseCode::get_400()
+        )
+    };
+    block.split("\r\n").map(|line| Header::build(line.to_string())).collect()
+}
+
+/// Private helper to build a [Part] from its headers and content.
+fn build_part(headers: Vec<Header>, data: PartData) -> WebResult<Part> {
+    let find_header = |name: &str| headers.iter().find(
+        |header| header.get_name().eq_ignore_ascii_case(name)
+    );
+
+    let disposition = match find_header("Content-Disposition") {
+        Some(header) => header.get_value().clone(),
+        None => return Result::Err(
+            ResponseCode::get_400()
+        )
+    };
+    let (disposition_type, parameters) = parse_parameters(&disposition);
+    if !disposition_type.eq_ignore_ascii_case("form-data") {
+        return Result::Err(
+            ResponseCode::get_400()
+        )
+    }
+
+    let parameter = |name: &str| parameters.iter().find(|(n, _)| n == name).map(|(_, v)| v.clone());
+    let name = match parameter("name") {
+        Some(name) => name,
+        None => return Result::Err(
+            ResponseCode::get_400()
+        )
+    };
+
+    // RFC 5987 "filename*=UTF-8''na%C3%AFve.txt" takes precedence
+    let extended_filename = parameter("filename*").and_then(|value| {
+        let (charset, encoded) = value.split_once("''")?;
+        if !charset.eq_ignore_ascii_case("utf-8") {
+            return None
+        }
+        String::from_utf8(percent_decode(encoded, false).ok()?).ok()
+    });
+    let filename = extended_filename.or(parameter("filename"));
+
+    let content_type = find_header("Content-Type").map(|header| header.get_value().clone());
+
+    Result::Ok(
+        Part {
+            name,
+            filename,
+            content_type,
+            headers,
+            data
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,156 @@
+use std::{env, io::{Error, Read}};
+
+use crate::web::{multipart::{parse, parse_parameters, MultipartLimits, PartData}, response::ResponseCode};
+
+/// Mock of a source that returns at most [step] bytes per read,
+/// so boundaries are split between reads.
+struct SlowReader<'a> {
+    data: &'a [u8],
+    step: usize
+}
+
+impl Read for SlowReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = buf.len().min(self.step).min(self.data.len());
+        buf[..len].copy_from_slice(&self.data[..len]);
+        self.data = &self.data[len..];
+        Ok(len)
+    }
+}
+
+/// Private helper to build a body with a text field and a binary file.
+fn body(file: &[u8]) -> Vec<u8> {
+    let mut body = Vec::from(
+        "preamble to be ignored\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        Cat: the \"best\"\r\n\
+        --XyZ  \r\n\
+        Content-Disposition: form-data; name=\"upload\"; filename=\"cat;1.bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\
+        \r\n".as_bytes()
+    );
+    body.extend_from_slice(file);
+    body.extend_from_slice("\r\n--XyZ--\r\nepilogue".as_bytes());
+    body
+}
+
+#[test]
+/// Test [parse] with text and binary parts, read in small pieces.
+fn parse_parts() {
+    // Binary content that resembles a delimiter
+    let file: Vec<u8> = [0, 255, 13, 10, b'-', b'-', b'X', b'y', 13, 10, 0].repeat(100);
+    let body = body(&file);
+
+    for step in [1, 7, 4096] {
+        let reader = SlowReader { data: &body, step };
+        let parts = parse(reader, "XyZ", &MultipartLimits::default()).unwrap();
+
+        assert!(parts.len() == 2);
+        assert!(parts[0].get_name() == "title");
+        assert!(parts[0].get_filename().is_none());
+        assert!(parts[0].text() == Some("Cat: the \"best\""));
+
+        assert!(parts[1].get_name() == "upload");
+        assert!(parts[1].get_filename() == Some("cat;1.bin"));
+        assert!(parts[1].get_content_type() == Some("application/octet-stream"));
+        assert!(parts[1].get_data().to_vec().unwrap() == file);
+    }
+}
+
+#[test]
+/// Test that a part larger than the threshold is moved to a temporary file.
+fn parse_to_file() {
+    let file = vec![7; 10_000];
+    let body = body(&file);
+    let limits = MultipartLimits::default()
+        .memory_threshold(1_000)
+        .temp_dir(env::temp_dir());
+
+    let mut parts = parse(body.as_slice(), "XyZ", &limits).unwrap();
+    assert!(matches!(parts[0].get_data(), PartData::Memory(_)));
+
+    let path = match parts.pop().unwrap().into_data() {
+        PartData::File(temp_file) => {
+            assert!(temp_file.get_size() == 10_000);
+            let mut content = Vec::new();
+            temp_file.open().unwrap().read_to_end(&mut content).unwrap();
+            assert!(content == file);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = temp_file.get_path().metadata().unwrap().permissions().mode();
+                assert!(mode & 0o777 == 0o600, "upload must be private, got {mode:o}");
+            }
+            temp_file.get_path().to_path_buf()
+        }
+        PartData::Memory(_) => panic!("part must be in a file")
+    };
+    assert!(!path.exists(), "temporary file must be removed on drop");
+}
+
+#[test]
+/// Test [parse] with part and total size limits.
+fn parse_limits() {
+    let body = body(&[1; 1_000]);
+
+    let limits = MultipartLimits::default().part_size(999);
+    assert!(parse(body.as_slice(), "XyZ", &limits).unwrap_err() == ResponseCode::get_413());
+
+    let limits = MultipartLimits::default().total_size(body.len() - 1);
+    assert!(parse(body.as_slice(), "XyZ", &limits).unwrap_err() == ResponseCode::get_413());
+
+    let limits = MultipartLimits::default().part_size(1_000).total_size(body.len());
+    assert!(parse(body.as_slice(), "XyZ", &limits).is_ok());
+}
+
+#[test]
+/// Test [parse] with malformed bodies.
+fn parse_fail() {
+    let cases = [
+        // No closing boundary
+        "--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1",
+        // No boundary at all
+        "just text",
+        // Missing name
+        "--b\r\nContent-Disposition: form-data\r\n\r\n1\r\n--b--",
+        // Missing Content-Disposition
+        "--b\r\nContent-Type: text/plain\r\n\r\n1\r\n--b--",
+        // Garbage after boundary
+        "--bogus\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\n1\r\n--b--",
+        // Malformed part header
+        "--b\r\nContent-Disposition form-data\r\n\r\n1\r\n--b--"
+    ];
+
+    for body in cases {
+        let code = parse(body.as_bytes(), "b", &MultipartLimits::default()).unwrap_err();
+        assert!(code == ResponseCode::get_400(), "{body:?} must be rejected");
+    }
+
+    let code = parse("--\r\n".as_bytes(), "", &MultipartLimits::default()).unwrap_err();
+    assert!(code == ResponseCode::get_400(), "empty boundary must be rejected");
+
+    // Empty body with only the closing boundary has no parts
+    assert!(parse("--b--".as_bytes(), "b", &MultipartLimits::default()).unwrap().is_empty());
+}
+
+#[test]
+/// Test [parse_parameters] with quoted, unquoted and extended values.
+fn parameters() {
+    let (value, parameters) = parse_parameters(
+        "form-data; name=\"a;b\"; FILENAME=\"c\\\"d.txt\"; filename*=UTF-8''na%C3%AFve.txt"
+    );
+    assert!(value == "form-data");
+    assert!(parameters == vec![
+        ("name".to_string(), "a;b".to_string()),
+        ("filename".to_string(), "c\"d.txt".to_string()),
+        ("filename*".to_string(), "UTF-8''na%C3%AFve.txt".to_string())
+    ]);
+
+    let body = "--b\r\nContent-Disposition: form-data; name=a; filename=x.txt; filename*=UTF-8''na%C3%AFve.txt\r\n\r\n\r\n--b--";
+    let parts = parse(body.as_bytes(), "b", &MultipartLimits::default()).unwrap();
+    assert!(parts[0].get_name() == "a");
+    assert!(parts[0].get_filename() == Some("naïve.txt"));
+    assert!(parts[0].text() == Some(""));
+}
@@ -1,17 +1,42 @@
-use std::{io::{BufRead, BufReader, Error, Read, Write}, net::TcpStream};
+use std::{
+    io::{BufRead, BufReader, Cursor, Error, ErrorKind, Read, Write},
+    net::TcpStream,
+    time::{Duration, Instant}
+};
 
+use crate::json::Value;
+use crate::web::cookie::Cookie;
+use crate::web::multipart::{self, parse_parameters, MultipartLimits, Part};
 use crate::web::response::{Header, Response, ResponseCode, Versions, WebResult};
+use crate::web::session::Session;
+use crate::web::uri::{parse_urlencoded, Target};
+
+/// Default limit on the body size, the body is read into memory before
+/// the request is handled. Matches [MultipartLimits] total size.
+/// Multipart bodies are streamed instead and bounded by [MultipartLimits].
+pub const MAX_BODY_SIZE: u64 = 32 * 1024 * 1024;
 
 #[derive(Debug)]
 struct StatusRequest {
     method: String,
-    uri: String,
+    target: Target,
     version: Versions
 }
 
+/// Checks for the "HTTP/<digit>" or "HTTP/<digit>.<digit>" version syntax.
+fn is_http_version(version: &str) -> bool {
+    match version.strip_prefix("HTTP/").map(|v| v.as_bytes()) {
+        Some([major]) => major.is_ascii_digit(),
+        Some([major, b'.', minor]) => major.is_ascii_digit() && minor.is_ascii_digit(),
+        _ => false
+    }
+}
+
 impl StatusRequest {
     /// Builds the [StatusRequest] based on the string representation.
-    /// Returns [ResponseCode] of 400 (HTTP 400) on fail.
+    /// Returns [ResponseCode] of 400 (HTTP 400) on fail, including a target
+    /// that [Target::parse] rejects. A well-formed version other than
+    /// HTTP/1.0 and HTTP/1.1 is answered with 505 (HTTP 505).
     /// 
     /// # Parameters
     /// line_str - a string representation with no \r\n at the end.
@@ -24,10 +49,13 @@ impl StatusRequest {
             )
         }
 
-        let version = if parts[2] == "HTTP/1.1" {
-            Versions::Http1_1
-        } else {
-            return Result::Err(
+        let version = match parts[2] {
+            "HTTP/1.1" => Versions::Http1_1,
+            "HTTP/1.0" => Versions::Http1_0,
+            other if is_http_version(other) => return Result::Err(
+                ResponseCode::get_505()
+            ),
+            _ => return Result::Err(
                 ResponseCode::get_400()
             )
         };
@@ -35,8 +63,8 @@ impl StatusRequest {
         Result::Ok(
             StatusRequest {
                 method: parts[0].to_string(),
-                uri: parts[1].to_string(),
-                version: version
+                target: Target::parse(parts[1])?,
+                version
             }
         )
     }
@@ -46,12 +74,207 @@ impl StatusRequest {
     }
 
     pub const fn get_uri(&self) -> &String {
-        &self.uri
+        self.target.get_raw()
+    }
+
+    pub const fn get_path(&self) -> &String {
+        self.target.get_path()
+    }
+
+    pub fn get_query(&self) -> Option<&str> {
+        self.target.get_query()
     }
 
     pub const fn get_version(&self) -> &Versions {
         &self.version
     }
+
+    /// The request line as the client sent it, with a normalized version.
+    fn line(&self) -> String {
+        format!("{} {} {}", self.method, self.get_uri(), self.version)
+    }
+}
+
+/// Limits on how long a client may take to deliver a request.
+/// [header] bounds the time to receive the status line and headers,
+/// [body] bounds the time to receive the body and [request] is an overall
+/// deadline for both. Each limit is a total, so a client trickling bytes
+/// cannot keep a [Worker](crate::worker_pool) busy by resetting a per-read timer.
+/// [write] is applied to the stream as is and bounds every write of the response.
+/// 
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// 
+/// use rns::web::request::Timeouts;
+/// 
+/// let timeouts = Timeouts::default()
+///     .header(Duration::from_secs(5))
+///     .request(Duration::from_secs(20));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    header: Duration,
+    body: Duration,
+    request: Duration,
+    write: Duration
+}
+
+impl Timeouts {
+    pub const fn header(mut self, duration: Duration) -> Timeouts {
+        self.header = duration;
+        self
+    }
+
+    pub const fn body(mut self, duration: Duration) -> Timeouts {
+        self.body = duration;
+        self
+    }
+
+    pub const fn request(mut self, duration: Duration) -> Timeouts {
+        self.request = duration;
+        self
+    }
+
+    pub const fn write(mut self, duration: Duration) -> Timeouts {
+        self.write = duration;
+        self
+    }
+
+    pub const fn get_header(&self) -> Duration {
+        self.header
+    }
+
+    pub const fn get_body(&self) -> Duration {
+        self.body
+    }
+
+    pub const fn get_request(&self) -> Duration {
+        self.request
+    }
+
+    pub const fn get_write(&self) -> Duration {
+        self.write
+    }
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            header: Duration::from_secs(10),
+            body: Duration::from_secs(30),
+            request: Duration::from_secs(60),
+            write: Duration::from_secs(30)
+        }
+    }
+}
+
+/// Streams that can limit how long a single blocking IO call may take.
+/// Implemented for [TcpStream]; implement it for mock streams in tests.
+pub trait TimeoutStream {
+    fn set_read_timeout(&self, duration: Option<Duration>) -> Result<(), Error>;
+    fn set_write_timeout(&self, duration: Option<Duration>) -> Result<(), Error>;
+}
+
+impl TimeoutStream for TcpStream {
+    fn set_read_timeout(&self, duration: Option<Duration>) -> Result<(), Error> {
+        TcpStream::set_read_timeout(self, duration)
+    }
+
+    fn set_write_timeout(&self, duration: Option<Duration>) -> Result<(), Error> {
+        TcpStream::set_write_timeout(self, duration)
+    }
+}
+
+/// Private reader that fails with [ErrorKind::TimedOut] once [deadline] passes.
+/// Before every read the stream's own read timeout is shrunk to the time left,
+/// so a read blocked on a silent client also returns in time.
+struct DeadlineReader<'a, T: Read + Write> {
+    stream: &'a mut T,
+    deadline: Option<Instant>,
+    set_timeout: fn(&T, Option<Duration>) -> Result<(), Error>
+}
+
+impl<T: Read + Write> Read for DeadlineReader<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Result::Err(
+                    Error::new(ErrorKind::TimedOut, "request deadline exceeded")
+                )
+            }
+            (self.set_timeout)(self.stream, Some(remaining))?;
+        }
+
+        self.stream.read(buf)
+    }
+}
+
+/// Maps a failed read to the code the client is answered with.
+/// A timed out socket read reports [ErrorKind::WouldBlock] on some platforms.
+/// Lines that are not UTF-8 report [ErrorKind::InvalidData], which is the client's fault.
+fn read_failure_code(err: &Error) -> ResponseCode {
+    match err.kind() {
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => ResponseCode::get_408(),
+        ErrorKind::InvalidData => ResponseCode::get_400(),
+        _ => ResponseCode::get_500()
+    }
+}
+
+/// Sends just the code to the client in the given version. Returns the code to
+/// be used as an error, or HTTP 500 if the client could not be answered.
+fn reject<T: Read + Write>(code: ResponseCode, version: Versions, stream: &mut T) -> ResponseCode {
+    match Response::respond_code(version, code.clone(), stream) {
+        Ok(_) => code,
+        Err(_) => ResponseCode::get_500()
+    }
+}
+
+/// A request that was answered with [code] while being read.
+/// [request_line] is known if the request line was valid, e.g. when
+/// a header or the body was rejected.
+#[derive(Debug)]
+pub(crate) struct Rejection {
+    code: ResponseCode,
+    request_line: Option<String>
+}
+
+impl Rejection {
+    pub(crate) const fn get_code(&self) -> &ResponseCode {
+        &self.code
+    }
+
+    pub(crate) fn get_request_line(&self) -> Option<&str> {
+        self.request_line.as_deref()
+    }
+}
+
+impl From<ResponseCode> for Rejection {
+    fn from(code: ResponseCode) -> Rejection {
+        Rejection {
+            code,
+            request_line: None
+        }
+    }
+}
+
+/// Same as [reject], after the request line was parsed.
+fn reject_request<T: Read + Write>(code: ResponseCode, status_line: &StatusRequest, stream: &mut T) -> Rejection {
+    Rejection {
+        code: reject(code, *status_line.get_version(), stream),
+        request_line: Some(status_line.line())
+    }
+}
+
+/// Private state of a body left on the stream for [RequestBackend::multipart].
+#[derive(Debug)]
+struct PendingBody<T> {
+    /// Bytes of the body that were read ahead with the headers.
+    buffered: Vec<u8>,
+    length: u64,
+    deadline: Option<Instant>,
+    set_timeout: fn(&T, Option<Duration>) -> Result<(), Error>
 }
 
 #[derive(Debug)]
@@ -59,6 +282,12 @@ pub struct RequestBackend<T: Read + Write> {
     status_line: StatusRequest,
     headers: Vec<Header>,
     body: Vec<u8>,
+    pending_body: Option<PendingBody<T>>,
+    bytes_received: u64,
+    session: Option<Session>,
+    response_headers: Vec<Header>,
+    response_code: Option<ResponseCode>,
+    bytes_sent: u64,
     response_stream: T
 }
 
@@ -67,6 +296,13 @@ impl<T: Read + Write> RequestBackend<T> {
     /// a simple http response will be sent to a client in case of failure
     /// (mainly HTTP 400 due to the request not adhereing to standard, although
     /// HTTP 500 is also possible if server suffers IO failure).
+    /// The body is framed by the Content-Length header and is empty without it.
+    /// A "multipart/form-data" body is left on the stream for [multipart].
+    /// Transfer-Encoding is not supported: a chunked body is answered with 411
+    /// (HTTP 411), other codings with 501 (HTTP 501), and either one along with
+    /// Content-Length with 400 (HTTP 400).
+    /// A Content-Length over [MAX_BODY_SIZE] is answered with 413 (HTTP 413)
+    /// before any of the body is read.
     /// 
     /// # Parameters
     /// stream - usually a [TcpStream] for a web server. Although, trait bounds are
@@ -74,8 +310,56 @@ impl<T: Read + Write> RequestBackend<T> {
     /// 
     /// # Examples
     /// Example is quite bulky for a docstring so kindly refer to the unit tests.
-    pub fn build(mut stream: T) -> WebResult<RequestBackend<T>> {
-        let mut buf_reader = BufReader::new(&mut stream);
+    pub fn build(stream: T) -> WebResult<RequestBackend<T>> {
+        fn no_timeout<T>(_stream: &T, _duration: Option<Duration>) -> Result<(), Error> {
+            Ok(())
+        }
+
+        Self::build_inner(stream, None, MAX_BODY_SIZE, no_timeout).map_err(|rejection| rejection.code)
+    }
+
+    /// Same as [build], but gives up with HTTP 408 if the client does not deliver
+    /// the request within [Timeouts]. The write timeout is applied to the stream
+    /// before parsing and stays in place for the response.
+    /// Bodies over [max_body] bytes are answered with HTTP 413.
+    pub fn build_timed(stream: T, timeouts: &Timeouts, max_body: u64) -> WebResult<RequestBackend<T>>
+    where
+        T: TimeoutStream
+    {
+        Self::build_served(stream, timeouts, max_body).map_err(|rejection| rejection.code)
+    }
+
+    /// Same as [build_timed], but the error tells the request line for the access log.
+    pub(crate) fn build_served(stream: T, timeouts: &Timeouts, max_body: u64) -> Result<RequestBackend<T>, Rejection>
+    where
+        T: TimeoutStream
+    {
+        if stream.set_write_timeout(Some(timeouts.get_write())).is_err() {
+            return Result::Err(
+                ResponseCode::get_500().into()
+            )
+        }
+
+        Self::build_inner(stream, Some(timeouts), max_body, T::set_read_timeout)
+    }
+
+    fn build_inner(
+        mut stream: T,
+        timeouts: Option<&Timeouts>,
+        max_body: u64,
+        set_timeout: fn(&T, Option<Duration>) -> Result<(), Error>
+    ) -> Result<RequestBackend<T>, Rejection> {
+        let start = Instant::now();
+        let request_deadline = timeouts.map(|t| start + t.get_request());
+        let header_deadline = timeouts.map(|t| (start + t.get_header()).min(start + t.get_request()));
+
+        let mut buf_reader = BufReader::new(
+            DeadlineReader {
+                stream: &mut stream,
+                deadline: header_deadline,
+                set_timeout
+            }
+        );
         let mut http_lines = buf_reader.by_ref().lines();
 
         // Build the status line: 
@@ -84,37 +368,34 @@ impl<T: Read + Write> RequestBackend<T> {
         // 3. Check if read is successful.
         // 4. Delegate to StatusRequest::build().
         let status_str = match http_lines.next() {
-            None => {                
-                let respond_result = Response::respond_code(
-                    Versions::Http1_1, 
-                    ResponseCode::get_400(), 
-                    &mut stream
-                );
-                return match respond_result {
-                    Ok(_) => Result::Err(
-                        ResponseCode::get_400()
-                    ),
-                    Err(_) => Result::Err(
-                        ResponseCode::get_500()
-                    ),
-                }
+            None => {
+                return Result::Err(
+                    reject(ResponseCode::get_400(), Versions::Http1_1, &mut stream).into()
+                )
             },
             Some(res) => res
         };
         let status_str = match status_str {
             Ok(s) => s,
-            Err(_) => { // Why would read fail here?
-                let _ = Response::respond_code(
-                    Versions::Http1_1, 
-                    ResponseCode::get_500(), 
-                    &mut stream
-                );
+            Err(err) => {
+                return Result::Err(
+                    reject(read_failure_code(&err), Versions::Http1_1, &mut stream).into()
+                )
+            }
+        };
+        // A request line rejected for its method or target may still tell the version
+        let version = match status_str.split_whitespace().nth(2) {
+            Some("HTTP/1.0") => Versions::Http1_0,
+            _ => Versions::Http1_1
+        };
+        let status_line = match StatusRequest::build(status_str) {
+            Ok(status_line) => status_line,
+            Err(code) => {
                 return Result::Err(
-                    ResponseCode::get_500()
+                    reject(code, version, &mut stream).into()
                 )
             }
         };
-        let status_line = StatusRequest::build(status_str)?;
 
         // Read the Headers
         let mut cr_lf_consumed = false; // cr, lf marks the end of headers, even if there were none
@@ -122,14 +403,9 @@ impl<T: Read + Write> RequestBackend<T> {
         for line_res in http_lines {
             let line = match line_res {
                 Ok(s) => s,
-                Err(_) => { // Why would read fail here?
-                    let _ = Response::respond_code(
-                        Versions::Http1_1, 
-                        ResponseCode::get_500(), 
-                        &mut stream
-                    );
+                Err(err) => {
                     return Result::Err(
-                        ResponseCode::get_500()
+                        reject_request(read_failure_code(&err), &status_line, &mut stream)
                     )
                 }
             };
@@ -140,71 +416,326 @@ impl<T: Read + Write> RequestBackend<T> {
                 break;
             }
 
-            let header = Header::build(line)?;
+            let header = match Header::build(line) {
+                Ok(header) => header,
+                Err(code) => {
+                    return Result::Err(
+                        reject_request(code, &status_line, &mut stream)
+                    )
+                }
+            };
             headers.push(header);
         }
 
         // Must see cr, nl after all headers
         if !cr_lf_consumed {
-            let respond_result = Response::respond_code(
-                Versions::Http1_1, 
-                ResponseCode::get_400(), 
-                &mut stream
-            );
-            return match respond_result {
-                Ok(_) => Result::Err(
-                    ResponseCode::get_400()
-                ),
-                Err(_) => Result::Err(
-                    ResponseCode::get_500()
-                ),
+            return Result::Err(
+                reject_request(ResponseCode::get_400(), &status_line, &mut stream)
+            )
+        };
+
+        // Transfer codings are not decoded. Clients that chunk a body can send
+        // it with Content-Length instead, both at once is a smuggling attempt.
+        if let Some(header) = headers.iter().find(
+            |header| header.get_name().eq_ignore_ascii_case("Transfer-Encoding")
+        ) {
+            let code = if headers.iter().any(|header| header.get_name().eq_ignore_ascii_case("Content-Length")) {
+                ResponseCode::get_400()
+            } else if header.get_value().rsplit(',').next().is_some_and(|coding| coding.trim().eq_ignore_ascii_case("chunked")) {
+                ResponseCode::get_411()
+            } else {
+                ResponseCode::get_501()
+            };
+            return Result::Err(
+                reject_request(code, &status_line, &mut stream)
+            )
+        }
+
+        // Body length is only known from Content-Length. Reading until EoF would
+        // block on a client that keeps the connection open.
+        let content_length = match headers.iter().find(
+            |header| header.get_name().eq_ignore_ascii_case("Content-Length")
+        ) {
+            None => 0,
+            Some(header) => match header.get_value().parse::<u64>() {
+                Ok(length) => length,
+                Err(_) => {
+                    return Result::Err(
+                        reject_request(ResponseCode::get_400(), &status_line, &mut stream)
+                    )
+                }
             }
         };
-        
+
+        let body_deadline = timeouts.map(|t| {
+            (Instant::now() + t.get_body()).min(request_deadline.unwrap())
+        });
+
+        // Uploads are parsed straight from the stream by multipart(),
+        // keeping what was read ahead with the headers
+        let is_multipart = headers.iter().any(|header| {
+            header.get_name().eq_ignore_ascii_case("Content-Type")
+                && header.get_value().split(';').next()
+                    .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("multipart/form-data"))
+        });
+        if is_multipart {
+            let buffered = buf_reader.buffer();
+            let buffered = buffered[..buffered.len().min(content_length as usize)].to_vec();
+            return Result::Ok(
+                RequestBackend {
+                    status_line,
+                    headers,
+                    body: Vec::new(),
+                    pending_body: Some(PendingBody { buffered, length: content_length, deadline: body_deadline, set_timeout }),
+                    bytes_received: 0,
+                    session: None,
+                    response_headers: Vec::new(),
+                    response_code: None,
+                    bytes_sent: 0,
+                    response_stream: stream
+                }
+            )
+        }
+
+        // Other bodies are held in memory, refuse them before reading
+        if content_length > max_body {
+            return Result::Err(
+                reject_request(ResponseCode::get_413(), &status_line, &mut stream)
+            )
+        }
+
         // Collect the request body
+        buf_reader.get_mut().deadline = body_deadline;
         let mut body = Vec::new();
-        if let Result::Err(_) = buf_reader.read_to_end(&mut body) { // Why would read fail here?
-            let _ = Response::respond_code(
-                Versions::Http1_1, 
-                ResponseCode::get_500(), 
-                &mut stream
-            );
+        if let Result::Err(err) = buf_reader.take(content_length).read_to_end(&mut body) {
             return Result::Err(
-                ResponseCode::get_500()
+                reject_request(read_failure_code(&err), &status_line, &mut stream)
             )
         };
+        if (body.len() as u64) < content_length {
+            return Result::Err(
+                reject_request(ResponseCode::get_400(), &status_line, &mut stream)
+            )
+        }
         
         Result::Ok(
             RequestBackend {
-                status_line: status_line,
-                headers: headers,
-                body: body,
+                status_line,
+                headers,
+                bytes_received: body.len() as u64,
+                body,
+                pending_body: None,
+                session: None,
+                response_headers: Vec::new(),
+                response_code: None,
+                bytes_sent: 0,
                 response_stream: stream
             }
         )
     }
 
     /// Send the response with the stored [response_stream].
+    /// The response is written in the client's version, so an HTTP/1.0 client
+    /// is never answered with HTTP/1.1.
+    /// Headers added by middleware (CORS, a session cookie) are sent along.
+    /// A HEAD request is answered with the headers only.
     pub fn respond(&mut self, response: &Response) -> Result<(), Error> {
-        response.respond(&mut self.response_stream)
+        let mut extra_headers = self.response_headers.clone();
+        extra_headers.extend(self.session.as_mut().and_then(Session::issue_cookie));
+        let with_body = self.get_method() != "HEAD";
+
+        self.response_code = Some(response.get_code().clone());
+        self.bytes_sent += response.respond_with(*self.get_version(), &extra_headers, with_body, &mut self.response_stream)?;
+        Result::Ok(())
+    }
+
+    /// Code of the last response sent with [respond], if any.
+    pub const fn get_response_code(&self) -> Option<&ResponseCode> {
+        self.response_code.as_ref()
+    }
+
+    /// Body bytes sent with [respond] so far, as counted by access logs.
+    pub const fn get_bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Body bytes read from the client so far. A multipart body only
+    /// counts once [multipart] read it.
+    pub const fn get_bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// A shorthand to answer with just the code in the client's version.
+    pub fn respond_code(&mut self, code: ResponseCode) -> Result<(), Error> {
+        self.respond(&Response::builder().status(code).build())
+    }
+
+    /// Session of the client, present when the server runs the session middleware.
+    pub const fn get_session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub const fn get_session_mut(&mut self) -> Option<&mut Session> {
+        self.session.as_mut()
+    }
+
+    pub(crate) fn set_session(&mut self, session: Session) {
+        self.session = Some(session);
+    }
+
+    pub(crate) fn take_session(&mut self) -> Option<Session> {
+        self.session.take()
+    }
+
+    /// Adds a header to any response sent with [respond].
+    pub(crate) fn add_response_header(&mut self, header: Header) {
+        self.response_headers.push(header);
     }
-    
+
     pub const fn get_method(&self) -> &String {
-        &self.status_line.get_method()
+        self.status_line.get_method()
     }
 
+    /// The request target exactly as sent by the client.
     pub const fn get_uri(&self) -> &String {
-        &self.status_line.get_uri()
+        self.status_line.get_uri()
+    }
+
+    /// The normalised, percent-decoded path used for routing. See [Target].
+    pub const fn get_path(&self) -> &String {
+        self.status_line.get_path()
+    }
+
+    /// The raw query string without the leading "?", if any.
+    pub fn get_query(&self) -> Option<&str> {
+        self.status_line.get_query()
     }
 
     pub const fn get_version(&self) -> &Versions {
-        &self.status_line.get_version()
+        self.status_line.get_version()
     }
 
     pub const fn get_headers(&self) -> &Vec<Header> {
         &self.headers
     }
 
+    /// Returns the first header with a matching name, compared case insensitively.
+    pub fn get_header(&self, name: &str) -> Option<&Header> {
+        self.headers.iter().find(|header| header.get_name().eq_ignore_ascii_case(name))
+    }
+
+    /// Parses cookies from all Cookie headers, see [Cookie::parse_header].
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.headers
+            .iter()
+            .filter(|header| header.get_name().eq_ignore_ascii_case("Cookie"))
+            .flat_map(|header| Cookie::parse_header(header.get_value()))
+            .collect()
+    }
+
+    /// Returns the value of the first cookie named [name].
+    pub fn get_cookie(&self, name: &str) -> Option<String> {
+        self.cookies()
+            .into_iter()
+            .find(|cookie| cookie.get_name() == name)
+            .map(|cookie| cookie.get_value().clone())
+    }
+
+    /// Returns the media type of the body in lower case, without parameters
+    /// such as charset. E.g. "application/json" for "Application/JSON; charset=utf-8".
+    pub fn get_media_type(&self) -> Option<String> {
+        self.get_header("Content-Type").map(|header| {
+            let value = header.get_value();
+            let media_type = value.split(';').next().unwrap_or(value);
+            media_type.trim().to_ascii_lowercase()
+        })
+    }
+
+    /// Parses the body as JSON.
+    /// Returns [ResponseCode] of 415 (HTTP 415) if Content-Type is not
+    /// "application/json" or a "+json" type and 400 (HTTP 400) if the body
+    /// is not strict JSON.
+    pub fn json(&self) -> WebResult<Value> {
+        match self.get_media_type() {
+            Some(media_type) if media_type == "application/json"
+                || (media_type.starts_with("application/") && media_type.ends_with("+json")) => {}
+            _ => return Result::Err(
+                ResponseCode::get_415()
+            )
+        }
+
+        let text = match std::str::from_utf8(&self.body) {
+            Ok(text) => text,
+            Err(_) => return Result::Err(
+                ResponseCode::get_400()
+            )
+        };
+
+        Value::parse(text).map_err(|_| ResponseCode::get_400())
+    }
+
+    /// Parses a "multipart/form-data" body, as sent by forms with file inputs.
+    /// The body is streamed from the client under the body deadline of
+    /// [build_timed], so only parts below [MultipartLimits] memory threshold
+    /// are held in memory and the rest goes straight to temporary files.
+    /// See [multipart::parse] for the errors, in addition to
+    /// [ResponseCode] of 415 (HTTP 415) on another Content-Type, 400 (HTTP 400)
+    /// if it has no boundary and 500 (HTTP 500) if the body was already parsed.
+    pub fn multipart(&mut self, limits: &MultipartLimits) -> WebResult<Vec<Part>> {
+        if self.get_media_type().as_deref() != Some("multipart/form-data") {
+            return Result::Err(
+                ResponseCode::get_415()
+            )
+        }
+
+        // Media type is present, so is the header
+        let content_type = self.get_header("Content-Type").unwrap().get_value();
+        let (_, parameters) = parse_parameters(content_type);
+        let boundary = match parameters.into_iter().find(|(name, _)| name == "boundary") {
+            Some((_, boundary)) => boundary,
+            None => return Result::Err(
+                ResponseCode::get_400()
+            )
+        };
+
+        // The stream can only be read once
+        let pending = match self.pending_body.take() {
+            Some(pending) => pending,
+            None => return Result::Err(
+                ResponseCode::get_500()
+            )
+        };
+        let reader = DeadlineReader {
+            stream: &mut self.response_stream,
+            deadline: pending.deadline,
+            set_timeout: pending.set_timeout
+        };
+        let mut body = Cursor::new(pending.buffered).chain(reader).take(pending.length);
+        let parts = multipart::parse(&mut body, &boundary, limits);
+        self.bytes_received = pending.length - body.limit();
+        parts
+    }
+
+    /// Parses the body of an HTML form as decoded name and value pairs,
+    /// see [parse_urlencoded].
+    /// Returns [ResponseCode] of 415 (HTTP 415) if Content-Type is not
+    /// "application/x-www-form-urlencoded" and 400 (HTTP 400) on bad encoding.
+    pub fn form(&self) -> WebResult<Vec<(String, String)>> {
+        if self.get_media_type().as_deref() != Some("application/x-www-form-urlencoded") {
+            return Result::Err(
+                ResponseCode::get_415()
+            )
+        }
+
+        match std::str::from_utf8(&self.body) {
+            Ok(text) => parse_urlencoded(text),
+            Err(_) => Result::Err(
+                ResponseCode::get_400()
+            )
+        }
+    }
+
+    /// The body read into memory, empty for "multipart/form-data", see [multipart].
     pub const fn get_body(&self) -> &Vec<u8> {
         &self.body
     }
@@ -1,6 +1,8 @@
-use std::{io::{Cursor, Read, Seek, SeekFrom}, iter::zip};
+use std::{io::{Cursor, Error, Read, Seek, SeekFrom, Write}, iter::zip, thread, time::Duration};
 
-use crate::web::{request::{RequestBackend, StatusRequest}, response::{Header, Response, ResponseCode, Versions}};
+use crate::json::Value;
+use crate::web::multipart::{MultipartLimits, PartData};
+use crate::web::{request::{RequestBackend, StatusRequest, TimeoutStream, Timeouts, MAX_BODY_SIZE}, response::{Header, Response, ResponseCode, Versions}};
 
 #[test]
 /// Test [StatusRequest] build method in normal operation.
@@ -8,6 +10,20 @@ fn build_status_request() {
     let status_str = "GET /test HTTP/1.1".to_string();
     let status_req = StatusRequest::build(status_str).unwrap();
     assert!(*status_req.get_version() == Versions::Http1_1);
+
+    let status_str = "GET /test HTTP/1.0".to_string();
+    let status_req = StatusRequest::build(status_str).unwrap();
+    assert!(*status_req.get_version() == Versions::Http1_0);
+}
+
+#[test]
+/// Test [StatusRequest] build method with well-formed, but unsupported versions.
+fn build_status_request_unsupported() {
+    for version in ["HTTP/2", "HTTP/2.0", "HTTP/3", "HTTP/0.9", "HTTP/1.2"] {
+        let status_str = format!("GET /test {version}");
+        let status_req = StatusRequest::build(status_str).unwrap_err();
+        assert!(status_req == ResponseCode::get_505(), "{version} must not be supported");
+    }
 }
 
 #[test]
@@ -39,19 +55,21 @@ type MockStream = Cursor<Vec<u8>>;
 fn build_request() {
     let stream: MockStream = Cursor::new(
         Vec::from(
-            "GET /test HTTP/1.1\r\nHost: www.example.com\r\nAccept-Language: en\r\n\r\n{\"meow\": 1}".as_bytes()
+            "GET /test HTTP/1.1\r\nHost: www.example.com\r\nAccept-Language: en\r\nContent-Length: 11\r\n\r\n{\"meow\": 1}".as_bytes()
         )
     );
     let req: RequestBackend<MockStream> = RequestBackend::build(stream).unwrap();
 
     assert!(req.get_method() == "GET");
     assert!(req.get_uri() == "/test");
+    assert!(req.get_path() == "/test");
+    assert!(req.get_query().is_none());
     assert!(*req.get_version() == Versions::Http1_1);
     assert!(*req.get_body() == "{\"meow\": 1}".as_bytes());
 
     for (result, actual) in zip(
         req.get_headers(), 
-        vec!["Host: www.example.com", "Accept-Language: en"]) 
+        vec!["Host: www.example.com", "Accept-Language: en", "Content-Length: 11"]) 
     {
         assert!(result.to_http_str() == actual);
     }
@@ -77,6 +95,15 @@ fn build_request_fail() {
     );
     let req = RequestBackend::build(stream).unwrap_err();
     assert!(req == ResponseCode::get_400(), "must fail on invalid format (missing cr, nl after headers)");
+
+    // Path traversal
+    let stream: MockStream = Cursor::new(
+        Vec::from(
+            "GET /static/../../etc/passwd HTTP/1.1\r\n\r\n".as_bytes()
+        )
+    );
+    let req = RequestBackend::build(stream).unwrap_err();
+    assert!(req == ResponseCode::get_400(), "must fail on path above the root");
 }
 
 #[test]
@@ -84,7 +111,7 @@ fn build_request_fail() {
 fn respond() {
     let stream: MockStream = Cursor::new(
         Vec::from(
-            "GET /test HTTP/1.1\r\nHost: www.example.com\r\nAccept-Language: en\r\n\r\n{\"meow\": 1}".as_bytes()
+            "GET /test HTTP/1.1\r\nHost: www.example.com\r\nAccept-Language: en\r\nContent-Length: 11\r\n\r\n{\"meow\": 1}".as_bytes()
         )
     );
     let mut req: RequestBackend<MockStream> = RequestBackend::build(stream).unwrap();
@@ -118,9 +145,418 @@ fn respond() {
     stream_copy.seek(SeekFrom::Start(0)).unwrap();
     stream_copy.read_to_end(&mut buf).unwrap();
     let actual_resp = Vec::from(
-        "GET /test HTTP/1.1\r\nHost: www.example.com\r\nAccept-Language: en\r\n\r\n{\"meow\": 1}\
-        HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nHello".as_bytes()
+        "GET /test HTTP/1.1\r\nHost: www.example.com\r\nAccept-Language: en\r\nContent-Length: 11\r\n\r\n{\"meow\": 1}\
+        HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nConnection: close\r\n\r\nHello".as_bytes()
     );
 
     assert!(buf == actual_resp);
 }
+
+/// Mock of a slow client that delivers one byte per read.
+#[derive(Debug)]
+struct TricklingStream {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+    delay: Duration
+}
+
+impl Read for TricklingStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        thread::sleep(self.delay);
+        let len = buf.len().min(1);
+        self.input.read(&mut buf[..len])
+    }
+}
+
+impl Write for TricklingStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl TimeoutStream for TricklingStream {
+    fn set_read_timeout(&self, _duration: Option<Duration>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn set_write_timeout(&self, _duration: Option<Duration>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[test]
+/// Test [RequestBackend] build_timed method against a client trickling bytes.
+fn build_request_timeout() {
+    let request = "GET /test HTTP/1.1\r\nHost: www.example.com\r\nContent-Length: 11\r\n\r\n{\"meow\": 1}";
+
+    // Headers are too slow
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::from_millis(2)
+    };
+    let timeouts = Timeouts::default().header(Duration::from_millis(40));
+    let code = RequestBackend::build_timed(stream, &timeouts, MAX_BODY_SIZE).unwrap_err();
+    assert!(code == ResponseCode::get_408(), "must time out while reading headers");
+
+    // Body is too slow
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::from_millis(2)
+    };
+    let timeouts = Timeouts::default().body(Duration::from_millis(5));
+    let code = RequestBackend::build_timed(stream, &timeouts, MAX_BODY_SIZE).unwrap_err();
+    assert!(code == ResponseCode::get_408(), "must time out while reading body");
+
+    // Whole request is too slow, although each part is in time
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::from_millis(2)
+    };
+    let timeouts = Timeouts::default()
+        .header(Duration::from_secs(10))
+        .body(Duration::from_secs(10))
+        .request(Duration::from_millis(40));
+    let code = RequestBackend::build_timed(stream, &timeouts, MAX_BODY_SIZE).unwrap_err();
+    assert!(code == ResponseCode::get_408(), "must time out on overall deadline");
+
+    // In time
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::ZERO
+    };
+    let req = RequestBackend::build_timed(stream, &Timeouts::default(), MAX_BODY_SIZE).unwrap();
+    assert!(*req.get_body() == "{\"meow\": 1}".as_bytes());
+}
+
+#[test]
+/// Test that a body over the limit is answered with 413 before it is read.
+fn build_request_too_large() {
+    let request_str = format!("POST /test HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_SIZE + 1);
+    let mut stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let code = RequestBackend::build(&mut stream).unwrap_err();
+    assert!(code == ResponseCode::get_413());
+    assert!(stream.get_ref()[request_str.len()..].starts_with("HTTP/1.1 413 Payload Too Large\r\n".as_bytes()));
+
+    let request = "POST /test HTTP/1.1\r\nContent-Length: 11\r\n\r\n{\"meow\": 1}";
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::ZERO
+    };
+    let code = RequestBackend::build_timed(stream, &Timeouts::default(), 10).unwrap_err();
+    assert!(code == ResponseCode::get_413());
+
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::ZERO
+    };
+    let req = RequestBackend::build_timed(stream, &Timeouts::default(), 11).unwrap();
+    assert!(*req.get_body() == "{\"meow\": 1}".as_bytes());
+}
+
+#[test]
+/// Test that a rejected request tells its request line once it was parsed.
+fn build_served_rejection() {
+    let cases = [
+        ("POST /test?a=1 HTTP/1.0\r\nContent-Length: 11\r\n\r\n{\"meow\": 1}", Some("POST /test?a=1 HTTP/1.0")),
+        ("POST /test HTTP/1.1\r\nBad Header: 1\r\n\r\n", Some("POST /test HTTP/1.1")),
+        ("POST HTTP/1.1\r\n\r\n", None)
+    ];
+
+    for (request, expected) in cases {
+        let stream = TricklingStream {
+            input: Cursor::new(Vec::from(request.as_bytes())),
+            output: Vec::new(),
+            delay: Duration::ZERO
+        };
+        let rejection = RequestBackend::build_served(stream, &Timeouts::default(), 10).unwrap_err();
+        assert!(rejection.get_request_line() == expected, "{request:?} logged as {rejection:?}");
+    }
+}
+
+#[test]
+/// Test [RequestBackend] build method with a body shorter than Content-Length.
+fn build_request_truncated_body() {
+    let stream: MockStream = Cursor::new(
+        Vec::from(
+            "POST /test HTTP/1.1\r\nContent-Length: 20\r\n\r\n{\"meow\": 1}".as_bytes()
+        )
+    );
+    let req = RequestBackend::build(stream).unwrap_err();
+    assert!(req == ResponseCode::get_400(), "must fail on truncated body");
+
+    let stream: MockStream = Cursor::new(
+        Vec::from(
+            "POST /test HTTP/1.1\r\nContent-Length: lots\r\n\r\n".as_bytes()
+        )
+    );
+    let req = RequestBackend::build(stream).unwrap_err();
+    assert!(req == ResponseCode::get_400(), "must fail on invalid Content-Length");
+}
+
+#[test]
+/// Test that bodies with a Transfer-Encoding are refused rather than left unread.
+fn build_request_transfer_encoding() {
+    let cases = [
+        ("Transfer-Encoding: chunked\r\n", ResponseCode::get_411()),
+        ("Transfer-Encoding: gzip, Chunked\r\n", ResponseCode::get_411()),
+        ("Transfer-Encoding: gzip\r\n", ResponseCode::get_501()),
+        ("Transfer-Encoding: chunked\r\nContent-Length: 3\r\n", ResponseCode::get_400())
+    ];
+
+    for (headers, expected) in cases {
+        let stream: MockStream = Cursor::new(
+            Vec::from(
+                format!("POST /test HTTP/1.1\r\n{headers}\r\n3\r\nabc\r\n0\r\n\r\n").as_bytes()
+            )
+        );
+        let code = RequestBackend::build(stream).unwrap_err();
+        assert!(code == expected, "{headers:?} answered with {code}");
+    }
+}
+
+#[test]
+/// Test that bytes that are not UTF-8 in the head are answered with 400.
+fn build_request_invalid_utf8() {
+    let mut request = Vec::from("GET /test HTTP/1.1\r\nX-Name: ".as_bytes());
+    request.extend([0xff, 0xfe]);
+    request.extend("\r\n\r\n".as_bytes());
+    let code = RequestBackend::build(Cursor::new(request)).unwrap_err();
+    assert!(code == ResponseCode::get_400());
+}
+
+#[test]
+/// Test that an HTTP/1.0 client is answered in HTTP/1.0 and that unsupported
+/// versions are answered with 505.
+fn respond_version() {
+    let stream: MockStream = Cursor::new(
+        Vec::from(
+            "GET /test HTTP/1.0\r\n\r\n".as_bytes()
+        )
+    );
+    let mut req: RequestBackend<MockStream> = RequestBackend::build(stream).unwrap();
+    req.respond_code(ResponseCode::get_200()).unwrap();
+
+    let written = &req.get_response_stream().get_ref()["GET /test HTTP/1.0\r\n\r\n".len()..];
+    assert!(written.starts_with("HTTP/1.0 200 OK\r\n".as_bytes()));
+
+    // Built with a newer version, still answered in HTTP/1.0
+    let resp = Response::new(Versions::Http1_1, ResponseCode::get_200(), Vec::new(), Vec::new());
+    let mut buf = Vec::new();
+    resp.respond_as(*req.get_version(), &mut Cursor::new(&mut buf)).unwrap();
+    assert!(buf.starts_with("HTTP/1.0 200 OK\r\n".as_bytes()));
+
+    let stream: MockStream = Cursor::new(
+        Vec::from(
+            "GET /test HTTP/2.0\r\n\r\n".as_bytes()
+        )
+    );
+    let req = RequestBackend::build(stream).unwrap_err();
+    assert!(req == ResponseCode::get_505());
+}
+
+#[test]
+/// Test that a rejected HTTP/1.0 request is answered in HTTP/1.0.
+fn reject_version() {
+    let request_str = "GET /test HTTP/1.0\r\nBad Header\r\n\r\n";
+    let mut stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let req = RequestBackend::build(&mut stream).unwrap_err();
+    assert!(req == ResponseCode::get_400());
+
+    let written = &stream.get_ref()[request_str.len()..];
+    assert!(written.starts_with("HTTP/1.0 400 Bad Request\r\n".as_bytes()));
+
+    // Rejected by the request line, whose version is still known
+    let request_str = "GET test HTTP/1.0\r\n\r\n";
+    let mut stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let req = RequestBackend::build(&mut stream).unwrap_err();
+    assert!(req == ResponseCode::get_400());
+
+    let written = &stream.get_ref()[request_str.len()..];
+    assert!(written.starts_with("HTTP/1.0 400 Bad Request\r\n".as_bytes()));
+}
+
+#[test]
+/// Test [RequestBackend] json method with matching and mismatching content types.
+fn json() {
+    let build = |content_type: &str, body: &str| {
+        let stream: MockStream = Cursor::new(
+            Vec::from(
+                format!(
+                    "POST /test HTTP/1.1\r\n{content_type}Content-Length: {}\r\n\r\n{body}",
+                    body.len()
+                ).as_bytes()
+            )
+        );
+        RequestBackend::build(stream).unwrap()
+    };
+
+    let req = build("Content-Type: application/json\r\n", "{\"meow\": 1}");
+    assert!(req.json().unwrap().get("meow").and_then(Value::as_i64) == Some(1));
+
+    let req = build("Content-Type: Application/JSON; charset=utf-8\r\n", "[]");
+    assert!(req.json().unwrap() == Value::Array(Vec::new()));
+
+    let req = build("Content-Type: application/problem+json\r\n", "null");
+    assert!(req.json().unwrap().is_null());
+
+    let req = build("Content-Type: application/json\r\n", "{\"meow\": 1,}");
+    assert!(req.json().unwrap_err() == ResponseCode::get_400());
+
+    let req = build("Content-Type: text/plain\r\n", "{\"meow\": 1}");
+    assert!(req.json().unwrap_err() == ResponseCode::get_415());
+
+    let req = build("", "{\"meow\": 1}");
+    assert!(req.json().unwrap_err() == ResponseCode::get_415());
+}
+
+#[test]
+/// Test [RequestBackend] form method with matching and mismatching content types.
+fn form() {
+    let build = |content_type: &str, body: &str| {
+        let stream: MockStream = Cursor::new(
+            Vec::from(
+                format!(
+                    "POST /test HTTP/1.1\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                ).as_bytes()
+            )
+        );
+        RequestBackend::build(stream).unwrap()
+    };
+
+    let req = build("application/x-www-form-urlencoded", "name=Neko+Chan&tag=a&tag=%C3%A9");
+    let form = req.form().unwrap();
+    assert!(form == vec![
+        ("name".to_string(), "Neko Chan".to_string()),
+        ("tag".to_string(), "a".to_string()),
+        ("tag".to_string(), "é".to_string())
+    ]);
+
+    let req = build("application/x-www-form-urlencoded; charset=UTF-8", "a=%2");
+    assert!(req.form().unwrap_err() == ResponseCode::get_400());
+
+    let req = build("multipart/form-data; boundary=x", "a=1");
+    assert!(req.form().unwrap_err() == ResponseCode::get_415());
+}
+
+#[test]
+/// Test [RequestBackend] multipart method with content type checks.
+fn multipart() {
+    let build = |content_type: &str, body: &str| {
+        let stream: MockStream = Cursor::new(
+            Vec::from(
+                format!(
+                    "POST /test HTTP/1.1\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                ).as_bytes()
+            )
+        );
+        RequestBackend::build(stream).unwrap()
+    };
+    let body = "--a:b\r\nContent-Disposition: form-data; name=\"meow\"\r\n\r\n1\r\n--a:b--\r\n";
+
+    let mut req = build("multipart/form-data; boundary=\"a:b\"", body);
+    let parts = req.multipart(&MultipartLimits::default()).unwrap();
+    assert!(parts.len() == 1);
+    assert!(parts[0].get_name() == "meow");
+    assert!(parts[0].text() == Some("1"));
+
+    let mut req = build("multipart/form-data", body);
+    assert!(req.multipart(&MultipartLimits::default()).unwrap_err() == ResponseCode::get_400());
+
+    let mut req = build("application/json", body);
+    assert!(req.multipart(&MultipartLimits::default()).unwrap_err() == ResponseCode::get_415());
+}
+
+#[test]
+/// Test that a multipart body is streamed from the client by [RequestBackend]
+/// multipart rather than read into memory by build.
+fn multipart_streamed() {
+    let upload = "x".repeat(10_000);
+    let body = format!(
+        "--b\r\nContent-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n{upload}\r\n--b--\r\n"
+    );
+    let request = format!(
+        "POST /upload HTTP/1.1\r\nContent-Type: multipart/form-data; boundary=b\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    let limits = MultipartLimits::default().memory_threshold(1024);
+
+    // Larger than the body limit, which only applies to bodies held in memory
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::ZERO
+    };
+    let mut req = RequestBackend::build_timed(stream, &Timeouts::default(), 10).unwrap();
+    assert!(req.get_body().is_empty());
+    assert!(req.get_bytes_received() == 0);
+
+    let parts = req.multipart(&limits).unwrap();
+    assert!(matches!(parts[0].get_data(), PartData::File(_)));
+    assert!(parts[0].get_data().to_vec().unwrap() == upload.as_bytes());
+    // The epilogue after the closing boundary may be left unread
+    assert!(req.get_bytes_received() > upload.len() as u64 && req.get_bytes_received() <= body.len() as u64);
+    assert!(req.multipart(&limits).unwrap_err() == ResponseCode::get_500(), "body can only be read once");
+
+    // Part of the body is read ahead with the headers
+    let stream: MockStream = Cursor::new(Vec::from(request.as_bytes()));
+    let mut req = RequestBackend::build(stream).unwrap();
+    let parts = req.multipart(&limits).unwrap();
+    assert!(parts[0].get_data().to_vec().unwrap() == upload.as_bytes());
+
+    // Truncated by the client
+    let stream: MockStream = Cursor::new(Vec::from(&request.as_bytes()[..request.len() - 10]));
+    let mut req = RequestBackend::build(stream).unwrap();
+    assert!(req.multipart(&limits).unwrap_err() == ResponseCode::get_400());
+
+    // Too slow to be streamed within the body timeout
+    let stream = TricklingStream {
+        input: Cursor::new(Vec::from(request.as_bytes())),
+        output: Vec::new(),
+        delay: Duration::from_millis(1)
+    };
+    let timeouts = Timeouts::default().body(Duration::from_millis(20));
+    let mut req = RequestBackend::build_timed(stream, &timeouts, MAX_BODY_SIZE).unwrap();
+    assert!(req.multipart(&limits).unwrap_err() == ResponseCode::get_408());
+}
+
+#[test]
+/// Test [RequestBackend] cookies method across multiple Cookie headers.
+fn cookies() {
+    let stream: MockStream = Cursor::new(
+        Vec::from(
+            "GET /test HTTP/1.1\r\nCookie: a=1; b=\"2\"\r\ncookie: c=3; bad value=4\r\n\r\n".as_bytes()
+        )
+    );
+    let req = RequestBackend::build(stream).unwrap();
+
+    let names: Vec<_> = req.cookies().iter().map(|c| c.get_name().clone()).collect();
+    assert!(names == vec!["a", "b", "c"]);
+    assert!(req.get_cookie("b").as_deref() == Some("2"));
+    assert!(req.get_cookie("d").is_none());
+}
+
+#[test]
+/// Test that HEAD is answered with the headers of the full response only.
+fn respond_head() {
+    let request_str = "HEAD /test HTTP/1.1\r\n\r\n";
+    let stream: MockStream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let mut req: RequestBackend<MockStream> = RequestBackend::build(stream).unwrap();
+    req.respond(&Response::text("hello")).unwrap();
+
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    assert!(written.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(written.contains("\r\nContent-Length: 5\r\n"));
+    assert!(written.ends_with("\r\n\r\n"));
+}
@@ -1,43 +1,98 @@
-use std::{fmt::{self, Display}, io::{Error, Read, Write}};
+use std::{fmt::Display, io::{self, Error, ErrorKind, Read, Write}, sync::Mutex};
 
-#[derive(Debug)]
+use crate::json::Value;
+use crate::web::cookie::SetCookie;
+use crate::date::DateTime;
+
+#[derive(Debug, Clone, Copy)]
 #[derive(PartialEq)]
 pub enum Versions {
+    Http1_0,
     Http1_1,
     Http2,
     Http3
 }
 
+impl Versions {
+    /// Whether "Transfer-Encoding: chunked" may be used in responses.
+    /// HTTP/1.0 clients only understand bodies delimited by closing the connection.
+    pub const fn supports_chunked(&self) -> bool {
+        matches!(self, Versions::Http1_1)
+    }
+}
+
 impl Display for Versions {
-    // Http versions above 1.1 are unlikely to be implemented
+    // Http versions above 1.1 are unlikely to be implemented,
+    // but are still displayed for diagnostics.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Versions::Http1_0 => write!(f, "HTTP/1.0"),
             Versions::Http1_1 => write!(f, "HTTP/1.1"),
-            _ => Result::Err(fmt::Error)
+            Versions::Http2 => write!(f, "HTTP/2"),
+            Versions::Http3 => write!(f, "HTTP/3")
         }
     }
 }
 
-#[derive(Debug)]
+/// Whether the string is a non-empty HTTP token, as required for header names.
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+#[derive(Debug, Clone)]
 pub struct Header {
     name: String,
     value: String
 }
 
 impl Header {
-    pub fn build(header_str: String) -> WebResult<Header> {
-        let parts: Vec<_> = header_str.split(":").collect();
+    /// Creates a header from already separated name and value,
+    /// e.g. for values that contain a colon. Control characters other than
+    /// tab are removed from the value, so it cannot end the header early.
+    ///
+    /// # Panics
+    /// Will panic if name is empty or has characters other than HTTP token characters.
+    /// Use [try_new] for names that are not known to be valid.
+    pub fn new(name: &str, value: &str) -> Header {
+        match Header::try_new(name, value) {
+            Ok(header) => header,
+            Err(_) => panic!("invalid header name {name:?}")
+        }
+    }
 
-        if parts.len() < 2 {
+    /// Same as [new], but returns [ResponseCode] of 500 (HTTP 500) on an invalid
+    /// name instead of panicking, since it is a server side bug.
+    pub fn try_new(name: &str, value: &str) -> WebResult<Header> {
+        if !is_token(name) {
             return Result::Err(
-                ResponseCode::get_400()
+                ResponseCode::get_500()
             )
         }
 
         Result::Ok(
             Header {
-                name: parts[0].trim().to_string(),
-                value: parts[1].trim().to_string()
+                name: name.to_string(),
+                value: value.chars().filter(|c| *c == '\t' || !c.is_ascii_control()).collect()
+            }
+        )
+    }
+
+    /// Builds the [Header] from "name: value". Only the first colon separates
+    /// name and value, since values like dates and URLs contain colons too.
+    /// Returns [ResponseCode] of 400 (HTTP 400) without a colon or if the name
+    /// is not a token, e.g. with whitespace before the colon.
+    pub fn build(header_str: String) -> WebResult<Header> {
+        let (name, value) = match header_str.split_once(':') {
+            Some(parts) if is_token(parts.0) => parts,
+            _ => return Result::Err(
+                ResponseCode::get_400()
+            )
+        };
+
+        Result::Ok(
+            Header {
+                name: name.to_string(),
+                value: value.trim().to_string()
             }
         )
     }
@@ -59,7 +114,7 @@ impl Header {
 /// Static variant is used in const public API for initializing predetermined
 /// responses.
 /// Dynamic variant is used in generic API to allow for custom reasons also.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum ReasonStorageSpecifier {
     Static(&'static str),
     Dynamic(String)
@@ -67,7 +122,7 @@ enum ReasonStorageSpecifier {
 
 /// This struct naturally serves as an error type in code and is used to
 /// construct meaningful HTTP responses.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResponseCode {
     code: usize,
     reason: ReasonStorageSpecifier
@@ -81,6 +136,10 @@ impl ResponseCode {
         }
     }
 
+    pub const fn get_code(&self) -> usize {
+        self.code
+    }
+
     pub const fn get_200() -> ResponseCode {
         ResponseCode {
             code: 200,
@@ -88,6 +147,34 @@ impl ResponseCode {
         }
     }
 
+    pub const fn get_204() -> ResponseCode {
+        ResponseCode {
+            code: 204,
+            reason: ReasonStorageSpecifier::Static("No Content")
+        }
+    }
+
+    pub const fn get_301() -> ResponseCode {
+        ResponseCode {
+            code: 301,
+            reason: ReasonStorageSpecifier::Static("Moved Permanently")
+        }
+    }
+
+    pub const fn get_302() -> ResponseCode {
+        ResponseCode {
+            code: 302,
+            reason: ReasonStorageSpecifier::Static("Found")
+        }
+    }
+
+    pub const fn get_303() -> ResponseCode {
+        ResponseCode {
+            code: 303,
+            reason: ReasonStorageSpecifier::Static("See Other")
+        }
+    }
+
     pub const fn get_400() -> ResponseCode {
         ResponseCode {
             code: 400,
@@ -123,6 +210,34 @@ impl ResponseCode {
         }
     }
 
+    pub const fn get_408() -> ResponseCode {
+        ResponseCode {
+            code: 408,
+            reason: ReasonStorageSpecifier::Static("Request Timeout")
+        }
+    }
+
+    pub const fn get_411() -> ResponseCode {
+        ResponseCode {
+            code: 411,
+            reason: ReasonStorageSpecifier::Static("Length Required")
+        }
+    }
+
+    pub const fn get_413() -> ResponseCode {
+        ResponseCode {
+            code: 413,
+            reason: ReasonStorageSpecifier::Static("Payload Too Large")
+        }
+    }
+
+    pub const fn get_415() -> ResponseCode {
+        ResponseCode {
+            code: 415,
+            reason: ReasonStorageSpecifier::Static("Unsupported Media Type")
+        }
+    }
+
     pub const fn get_418() -> ResponseCode {
         ResponseCode {
             code: 418,
@@ -143,6 +258,27 @@ impl ResponseCode {
             reason: ReasonStorageSpecifier::Static("Internal Server Error")
         }
     }
+
+    pub const fn get_501() -> ResponseCode {
+        ResponseCode {
+            code: 501,
+            reason: ReasonStorageSpecifier::Static("Not Implemented")
+        }
+    }
+
+    pub const fn get_503() -> ResponseCode {
+        ResponseCode {
+            code: 503,
+            reason: ReasonStorageSpecifier::Static("Service Unavailable")
+        }
+    }
+
+    pub const fn get_505() -> ResponseCode {
+        ResponseCode {
+            code: 505,
+            reason: ReasonStorageSpecifier::Static("HTTP Version Not Supported")
+        }
+    }
 }
 
 impl PartialEq for ResponseCode {
@@ -171,8 +307,8 @@ pub struct StatusResponse {
 impl StatusResponse {
     pub fn new(version: Versions, code: ResponseCode) -> StatusResponse {
         StatusResponse {
-            version: version,
-            code: code
+            version,
+            code
         }
     }
 }
@@ -183,10 +319,50 @@ impl Display for StatusResponse {
     }
 }
 
+/// Source of a streamed body, see [Body].
+enum Stream {
+    Reader(Box<dyn Read + Send>),
+    Chunks(Box<dyn Iterator<Item = Vec<u8>> + Send>)
+}
+
+/// Response body is either buffered or streamed from a source that is
+/// consumed by the first write, hence the [Mutex] to write from `&self`.
+/// A streamed body of known [length] is framed by Content-Length.
+/// Otherwise it's sent with "Transfer-Encoding: chunked", or delimited
+/// by closing the connection for clients that don't support it.
+enum Body {
+    Bytes(Vec<u8>),
+    Stream {
+        source: Mutex<Option<Stream>>,
+        length: Option<u64>,
+        trailers: Vec<Header>
+    }
+}
+
+/// Size of a chunk when streaming from a reader.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// An HTTP response. Either created positionally with [new], or with
+/// [builder] and its shortcuts that also fill in framing headers.
+/// 
+/// # Example
+/// ```
+/// use rns::web::response::{Response, ResponseCode};
+/// 
+/// let response = Response::builder()
+///     .status(ResponseCode::get_200())
+///     .header("Content-Type", "text/plain")
+///     .body("Meow")
+///     .build();
+/// 
+/// assert!(response.get_header("Content-Length").unwrap().get_value() == "4");
+/// 
+/// let response = Response::redirect("/login");
+/// ```
 pub struct Response {
     status_line: StatusResponse,
     headers: Vec<Header>,
-    body: Vec<u8>
+    body: Body
 }
 
 impl Response {
@@ -196,33 +372,129 @@ impl Response {
     ) -> Response {
         Response { 
             status_line: StatusResponse::new(version, code),
-            headers: headers,
-            body: body 
+            headers,
+            body: Body::Bytes(body)
         }
     }
 
-    fn get_status_line_str(&self) -> String {
-        self.status_line.to_string()
+    /// Starts a [ResponseBuilder] with HTTP 200 and no headers or body.
+    pub fn builder() -> ResponseBuilder {
+        ResponseBuilder::new()
     }
 
-    fn get_headers_str(&self) -> impl Iterator<Item = String> {
-        self.headers.iter().map(|header| header.to_http_str())
+    /// HTTP 200 with a plain text body.
+    pub fn text(body: &str) -> Response {
+        Response::builder()
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(body)
+            .build()
+    }
+
+    /// HTTP 200 with an HTML body.
+    pub fn html(body: &str) -> Response {
+        Response::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(body)
+            .build()
+    }
+
+    /// HTTP 200 with a serialized JSON body.
+    pub fn json(value: &Value) -> Response {
+        Response::builder()
+            .header("Content-Type", "application/json")
+            .body(value.to_string())
+            .build()
+    }
+
+    /// HTTP 302 to [location]. Use [builder] for other redirect codes.
+    /// Control characters in location are removed, see [Header::new].
+    pub fn redirect(location: &str) -> Response {
+        Response::builder()
+            .status(ResponseCode::get_302())
+            .header("Location", location)
+            .build()
+    }
+
+    /// HTTP 204 with no body.
+    pub fn no_content() -> Response {
+        Response::builder()
+            .status(ResponseCode::get_204())
+            .build()
+    }
+
+    pub const fn get_code(&self) -> &ResponseCode {
+        &self.status_line.code
     }
 
-    fn get_body(&self) -> &Vec<u8> {
-        &self.body
+    pub const fn get_headers(&self) -> &Vec<Header> {
+        &self.headers
+    }
+
+    /// Returns the first header with a matching name, compared case insensitively.
+    pub fn get_header(&self, name: &str) -> Option<&Header> {
+        self.headers.iter().find(|header| header.get_name().eq_ignore_ascii_case(name))
+    }
+
+    fn get_headers_str(&self) -> impl Iterator<Item = String> {
+        self.headers.iter().map(|header| header.to_http_str())
     }
 
     /// Writes own contents to the provided stream.
     /// [Result] will return [Err] on any IO failure.
     pub fn respond<T: Read + Write>(&self, stream: &mut T) -> Result<(), Error> {
+        self.respond_as(self.status_line.version, stream)
+    }
+
+    /// Same as [respond], but the status line states [version] instead of
+    /// the one the response was created with. Used to answer in a version
+    /// the client understands.
+    pub fn respond_as<T: Read + Write>(
+        &self,
+        version: Versions,
+        stream: &mut T
+    ) -> Result<(), Error> {
+        self.respond_with(version, &[], true, stream).map(|_| ())
+    }
+
+    /// Same as [respond_as], but [extra_headers] are written after own headers.
+    /// Used by middleware that adds headers to every response, e.g. sessions.
+    /// Without [with_body] only the head is written, as an answer to HEAD
+    /// that keeps the headers (including Content-Length) of the full response.
+    /// Returns the number of bytes written after the head.
+    /// Connections are closed after one response, which is announced with
+    /// "Connection: close" unless a Connection header was set.
+    pub(crate) fn respond_with<T: Read + Write>(
+        &self,
+        version: Versions,
+        extra_headers: &[Header],
+        with_body: bool,
+        stream: &mut T
+    ) -> Result<u64, Error> {
+        // Unknown length of a streamed body is framed based on the version
+        let chunked = version.supports_chunked();
+        let mut framing_headers = Vec::new();
+        if let Body::Stream { length: None, trailers, .. } = &self.body
+            && chunked {
+            framing_headers.push(Header::new("Transfer-Encoding", "chunked"));
+            if !trailers.is_empty() {
+                let names: Vec<_> = trailers.iter().map(|t| t.get_name().as_str()).collect();
+                framing_headers.push(Header::new("Trailer", &names.join(", ")));
+            }
+        }
+        let has_connection = self.get_header("Connection").is_some()
+            || extra_headers.iter().any(|header| header.get_name().eq_ignore_ascii_case("Connection"));
+        if !has_connection {
+            framing_headers.push(Header::new("Connection", "close"));
+        }
+
         // Write status
-        let status = self.get_status_line_str();
+        let status = StatusResponse::new(version, self.status_line.code.clone()).to_string();
         stream.write_all(status.as_bytes())?;
         stream.write_all("\r\n".as_bytes())?;
 
         // Write headers
-        for header_str in self.get_headers_str() {
+        let added_headers_str = extra_headers.iter().chain(&framing_headers).map(|header| header.to_http_str());
+        for header_str in self.get_headers_str().chain(added_headers_str) {
             stream.write_all(header_str.as_bytes())?;
             stream.write_all("\r\n".as_bytes())?;
         };
@@ -230,22 +502,305 @@ impl Response {
         // End headers
         stream.write_all("\r\n".as_bytes())?;
 
-        // Write body
-        stream.write_all(self.get_body())?;
+        if !with_body {
+            return Result::Ok(0)
+        }
 
-        Result::Ok(())
+        // Write body
+        let mut stream = CountingWriter { inner: stream, count: 0 };
+        match &self.body {
+            Body::Bytes(bytes) => stream.write_all(bytes)?,
+            Body::Stream { source, length, trailers } => {
+                let source = source.lock().unwrap().take().ok_or(
+                    Error::other("streamed body was already sent")
+                )?;
+
+                match length {
+                    Some(length) => write_sized(source, *length, &mut stream)?,
+                    None if chunked => write_chunked(source, trailers, &mut stream)?,
+                    None => write_until_end(source, &mut stream)?
+                }
+            }
+        }
+        Result::Ok(stream.count)
     }
 
     /// A shorthand to send just the code in response with a IO related [Result].
+    /// Framing headers are added as done by [ResponseBuilder].
     pub fn respond_code<T: Read + Write>(
         version: Versions,
         code: ResponseCode,
         stream: &mut T
     ) -> Result<(), Error> {
-        let response = Response::new(version, code, Vec::new(), Vec::new());
+        let response = Response::builder().version(version).status(code).build();
         response.respond(stream)
     }
 }
 
+/// Counts the bytes that pass through to the inner stream.
+struct CountingWriter<'a, T: Write> {
+    inner: &'a mut T,
+    count: u64
+}
+
+impl<T: Write> Write for CountingWriter<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Result::Ok(n)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+/// Writes exactly [length] bytes from the source.
+fn write_sized<T: Write>(source: Stream, length: u64, stream: &mut T) -> Result<(), Error> {
+    let mut written = 0;
+    match source {
+        Stream::Reader(reader) => {
+            written = io::copy(&mut reader.take(length), stream)?;
+        }
+        Stream::Chunks(chunks) => {
+            for chunk in chunks {
+                let chunk = &chunk[..chunk.len().min((length - written) as usize)];
+                stream.write_all(chunk)?;
+                written += chunk.len() as u64;
+                // The rest of the source is not sent, it may never end
+                if written >= length {
+                    break
+                }
+            }
+        }
+    }
+
+    // Client waits for bytes that never come, the connection must be dropped
+    if written < length {
+        return Result::Err(
+            Error::new(ErrorKind::UnexpectedEof, "streamed body is shorter than its length")
+        )
+    }
+
+    Result::Ok(())
+}
+
+/// Writes the source with chunked transfer-encoding, followed by trailers.
+fn write_chunked<T: Write>(source: Stream, trailers: &[Header], stream: &mut T) -> Result<(), Error> {
+    let mut write_chunk = |chunk: &[u8]| -> Result<(), Error> {
+        // Empty chunk would end the body early
+        if chunk.is_empty() {
+            return Result::Ok(())
+        }
+        stream.write_all(format!("{:X}\r\n", chunk.len()).as_bytes())?;
+        stream.write_all(chunk)?;
+        stream.write_all("\r\n".as_bytes())
+    };
+
+    match source {
+        Stream::Reader(mut reader) => {
+            let mut buf = vec![0; CHUNK_SIZE];
+            loop {
+                let n = match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => return Result::Err(err)
+                };
+                write_chunk(&buf[..n])?;
+            }
+        }
+        Stream::Chunks(chunks) => {
+            for chunk in chunks {
+                write_chunk(&chunk)?;
+            }
+        }
+    }
+
+    stream.write_all("0\r\n".as_bytes())?;
+    for trailer in trailers {
+        stream.write_all(trailer.to_http_str().as_bytes())?;
+        stream.write_all("\r\n".as_bytes())?;
+    }
+    stream.write_all("\r\n".as_bytes())
+}
+
+/// Writes the whole source, the end of body is marked by closing the connection.
+fn write_until_end<T: Write>(source: Stream, stream: &mut T) -> Result<(), Error> {
+    match source {
+        Stream::Reader(mut reader) => {
+            io::copy(&mut reader, stream)?;
+        }
+        Stream::Chunks(chunks) => {
+            for chunk in chunks {
+                stream.write_all(&chunk)?;
+            }
+        }
+    }
+
+    Result::Ok(())
+}
+
+/// Fluent construction of a [Response]. [build] adds the framing headers
+/// clients rely on, unless they were set explicitly:
+/// 1. Content-Length, except for 1xx and 204 responses that can't have a body
+///    and 304, whose Content-Length would be the one of the full resource;
+/// 2. Date, as the current time;
+/// 3. Server, as "rns/<crate version>".
+/// 
+/// Streamed bodies of unknown length get their framing when written,
+/// since it depends on the version of the client.
+pub struct ResponseBuilder {
+    version: Versions,
+    code: ResponseCode,
+    headers: Vec<Header>,
+    body: Body
+}
+
+impl ResponseBuilder {
+    pub fn new() -> ResponseBuilder {
+        ResponseBuilder {
+            version: Versions::Http1_1,
+            code: ResponseCode::get_200(),
+            headers: Vec::new(),
+            body: Body::Bytes(Vec::new())
+        }
+    }
+
+    pub fn version(mut self, version: Versions) -> ResponseBuilder {
+        self.version = version;
+        self
+    }
+
+    pub fn status(mut self, code: ResponseCode) -> ResponseBuilder {
+        self.code = code;
+        self
+    }
+
+    /// Appends a header, so repeated names (e.g. Set-Cookie) are kept.
+    ///
+    /// # Panics
+    /// Will panic if name is not a valid header name, see [Header::new].
+    /// Use [try_header] for names that are not known to be valid.
+    pub fn header(mut self, name: &str, value: &str) -> ResponseBuilder {
+        self.headers.push(Header::new(name, value));
+        self
+    }
+
+    /// Same as [header], but fails as [Header::try_new] does instead of panicking.
+    pub fn try_header(mut self, name: &str, value: &str) -> WebResult<ResponseBuilder> {
+        self.headers.push(Header::try_new(name, value)?);
+        Result::Ok(self)
+    }
+
+    /// Appends a Set-Cookie header. Fails as [SetCookie::build] does.
+    pub fn cookie(mut self, cookie: &SetCookie) -> WebResult<ResponseBuilder> {
+        self.headers.push(cookie.build()?);
+        Result::Ok(self)
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> ResponseBuilder {
+        self.body = Body::Bytes(body.into());
+        self
+    }
+
+    /// Streams the body from [reader] without buffering it.
+    /// 
+    /// # Parameters
+    /// reader - read until the end, or until [length] bytes if known.
+    /// length - if known, body is sent with Content-Length, otherwise chunked.
+    pub fn body_reader(
+        mut self,
+        reader: impl Read + Send + 'static,
+        length: Option<u64>
+    ) -> ResponseBuilder {
+        self.body = Body::Stream {
+            source: Mutex::new(Some(Stream::Reader(Box::new(reader)))),
+            length,
+            trailers: self.take_trailers()
+        };
+        self
+    }
+
+    /// Streams the body as produced by [chunks], e.g. a report generated row by row.
+    /// Same [length] semantics as in [body_reader].
+    pub fn body_chunks(
+        mut self,
+        chunks: impl Iterator<Item = Vec<u8>> + Send + 'static,
+        length: Option<u64>
+    ) -> ResponseBuilder {
+        self.body = Body::Stream {
+            source: Mutex::new(Some(Stream::Chunks(Box::new(chunks)))),
+            length,
+            trailers: self.take_trailers()
+        };
+        self
+    }
+
+    /// Adds a trailer sent after a chunked body. Ignored for other bodies,
+    /// so it must be set after [body_reader] or [body_chunks].
+    ///
+    /// # Panics
+    /// Will panic if name is not a valid header name, see [Header::new].
+    /// Use [try_trailer] for names that are not known to be valid.
+    pub fn trailer(mut self, name: &str, value: &str) -> ResponseBuilder {
+        if let Body::Stream { trailers, .. } = &mut self.body {
+            trailers.push(Header::new(name, value));
+        }
+        self
+    }
+
+    /// Same as [trailer], but fails as [Header::try_new] does instead of panicking.
+    pub fn try_trailer(mut self, name: &str, value: &str) -> WebResult<ResponseBuilder> {
+        let header = Header::try_new(name, value)?;
+        if let Body::Stream { trailers, .. } = &mut self.body {
+            trailers.push(header);
+        }
+        Result::Ok(self)
+    }
+
+    /// Private helper to keep trailers when the body source is replaced.
+    fn take_trailers(&mut self) -> Vec<Header> {
+        match &mut self.body {
+            Body::Stream { trailers, .. } => std::mem::take(trailers),
+            Body::Bytes(_) => Vec::new()
+        }
+    }
+
+    pub fn build(mut self) -> Response {
+        let has_header = |headers: &Vec<Header>, name: &str| headers.iter().any(
+            |header| header.get_name().eq_ignore_ascii_case(name)
+        );
+
+        let length = match &self.body {
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Stream { length, .. } => *length
+        };
+        let code = self.code.get_code();
+        if let Some(length) = length
+            && code >= 200 && code != 204 && code != 304 && !has_header(&self.headers, "Content-Length") {
+            self.headers.push(Header::new("Content-Length", &length.to_string()));
+        }
+        if !has_header(&self.headers, "Date") {
+            self.headers.push(Header::new("Date", &DateTime::now().to_http_str()));
+        }
+        if !has_header(&self.headers, "Server") {
+            self.headers.push(Header::new("Server", concat!("rns/", env!("CARGO_PKG_VERSION"))));
+        }
+
+        Response {
+            status_line: StatusResponse::new(self.version, self.code),
+            headers: self.headers,
+            body: self.body
+        }
+    }
+}
+
+impl Default for ResponseBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests;
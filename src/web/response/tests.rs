@@ -1,5 +1,7 @@
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
+use crate::json::Value;
+use crate::web::cookie::SetCookie;
 use crate::web::response::{Header, Response, ResponseCode, Versions};
 
 #[test]
@@ -20,6 +22,13 @@ fn build_header() {
     assert!(header.get_name() == "Host");
     assert!(header.get_value() == "www.example.com");
     assert!(header.to_http_str() == "Host: www.example.com");
+
+    // Colons in value
+    let header_str = "Host: localhost:8080".to_string();
+    let header = Header::build(header_str.clone()).unwrap();
+
+    assert!(header.get_name() == "Host");
+    assert!(header.get_value() == "localhost:8080");
 }
 
 #[test]
@@ -30,6 +39,41 @@ fn build_header_fail() {
     let header = Header::build(header_str.clone()).unwrap_err();
 
     assert!(header == ResponseCode::get_400());
+
+    // Names must be tokens, without whitespace around them
+    for header_str in ["Host : www.example.com", ": www.example.com", " Host: www.example.com", "Ho st: x"] {
+        let header = Header::build(header_str.to_string()).unwrap_err();
+        assert!(header == ResponseCode::get_400(), "{header_str:?} must be rejected");
+    }
+}
+
+#[test]
+/// Test that [Header] new removes control characters from values.
+fn new_header() {
+    let header = Header::new("X-Test", "a\r\nSet-Cookie: b\tc");
+    assert!(header.get_name() == "X-Test");
+    assert!(header.get_value() == "aSet-Cookie: b\tc");
+}
+
+#[test]
+#[should_panic(expected = "invalid header name")]
+/// Test that [Header] new panics on a name with characters outside of a token.
+fn new_header_name() {
+    Header::new("X-Test\r\nSet-Cookie", "a");
+}
+
+#[test]
+/// Test that the fallible [Header] and [ResponseBuilder](super::ResponseBuilder)
+/// methods fail with 500 on invalid names instead of panicking.
+fn try_header() {
+    assert!(Header::try_new("X-Test", "a").is_ok());
+    assert!(Header::try_new("", "a").unwrap_err() == ResponseCode::get_500());
+    assert!(Header::try_new("X Test", "a").unwrap_err() == ResponseCode::get_500());
+
+    let resp = Response::builder().try_header("X-Test", "a").unwrap().build();
+    assert!(resp.get_header("X-Test").unwrap().get_value() == "a");
+    assert!(Response::builder().try_header("X-Test:", "a").is_err());
+    assert!(Response::builder().body_chunks(Vec::new().into_iter(), None).try_trailer("X\nY", "a").is_err());
 }
 
 #[test]
@@ -79,8 +123,193 @@ fn respond() {
     stream.read_to_end(&mut buf).unwrap();
     
     let actual_resp = Vec::from(
-        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nHello".as_bytes()
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nConnection: close\r\n\r\nHello".as_bytes()
     );
 
     assert!(buf == actual_resp);
 }
+
+#[test]
+/// Test that every [Versions] variant can be displayed.
+fn display_versions() {
+    assert!(Versions::Http1_0.to_string() == "HTTP/1.0");
+    assert!(Versions::Http1_1.to_string() == "HTTP/1.1");
+    assert!(Versions::Http2.to_string() == "HTTP/2");
+    assert!(Versions::Http3.to_string() == "HTTP/3");
+
+    assert!(!Versions::Http1_0.supports_chunked());
+    assert!(Versions::Http1_1.supports_chunked());
+}
+
+#[test]
+/// Test [ResponseBuilder] with automatic framing headers.
+fn builder() {
+    let resp = Response::builder()
+        .status(ResponseCode::get_404())
+        .header("Content-Type", "text/plain")
+        .header("Set-Cookie", "a=1")
+        .header("Set-Cookie", "b=2")
+        .body("Not here")
+        .build();
+
+    assert!(*resp.get_code() == ResponseCode::get_404());
+    assert!(resp.get_header("content-type").unwrap().get_value() == "text/plain");
+    assert!(resp.get_header("Content-Length").unwrap().get_value() == "8");
+    assert!(resp.get_header("Date").unwrap().get_value().ends_with(" GMT"));
+    assert!(resp.get_header("Server").unwrap().get_value().starts_with("rns/"));
+    assert!(resp.get_headers().iter().filter(|h| h.get_name() == "Set-Cookie").count() == 2);
+
+    // Explicit headers are not overwritten
+    let resp = Response::builder()
+        .header("Date", "Sun, 06 Nov 1994 08:49:37 GMT")
+        .header("content-length", "0")
+        .build();
+    assert!(resp.get_header("Date").unwrap().get_value() == "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert!(resp.get_headers().iter().filter(|h| h.get_name().eq_ignore_ascii_case("Content-Length")).count() == 1);
+}
+
+#[test]
+/// Test [Response] shortcuts.
+fn shortcuts() {
+    let resp = Response::text("Meow");
+    assert!(*resp.get_code() == ResponseCode::get_200());
+    assert!(resp.get_header("Content-Type").unwrap().get_value() == "text/plain; charset=utf-8");
+    assert!(resp.get_header("Content-Length").unwrap().get_value() == "4");
+
+    let resp = Response::html("<p>Meow</p>");
+    assert!(resp.get_header("Content-Type").unwrap().get_value() == "text/html; charset=utf-8");
+    assert!(resp.get_header("Content-Length").unwrap().get_value() == "11");
+
+    let resp = Response::redirect("/login?next=/a");
+    assert!(*resp.get_code() == ResponseCode::get_302());
+    assert!(resp.get_header("Location").unwrap().get_value() == "/login?next=/a");
+    assert!(resp.get_header("Content-Length").unwrap().get_value() == "0");
+
+    // Line breaks in the target must not split the Location header
+    let resp = Response::redirect("/a\r\nSet-Cookie: id=1");
+    assert!(resp.get_header("Location").unwrap().get_value() == "/aSet-Cookie: id=1");
+    assert!(resp.get_header("Set-Cookie").is_none());
+    let out = written(&resp, Versions::Http1_1);
+    assert!(out.contains("\r\nLocation: /aSet-Cookie: id=1\r\n"));
+    assert!(!out.contains("\r\nSet-Cookie"));
+
+    let resp = Response::no_content();
+    assert!(*resp.get_code() == ResponseCode::get_204());
+    assert!(resp.get_header("Content-Length").is_none(), "204 must not have Content-Length");
+
+    let resp = Response::builder().status(ResponseCode::new(304, "Not Modified".to_string())).build();
+    assert!(resp.get_header("Content-Length").is_none(), "304 must not have Content-Length");
+
+    // A Connection header of the handler is kept as the only one
+    let resp = Response::builder().header("Connection", "upgrade").build();
+    let out = written(&resp, Versions::Http1_1);
+    assert!(out.contains("\r\nConnection: upgrade\r\n"));
+    assert!(!out.contains("\r\nConnection: close\r\n"));
+
+    let mut stream: MockStream = Cursor::new(Vec::new());
+    Response::respond_code(Versions::Http1_1, ResponseCode::get_400(), &mut stream).unwrap();
+    let written = String::from_utf8(stream.into_inner()).unwrap();
+    assert!(written.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(written.contains("\r\nContent-Length: 0\r\n"));
+    assert!(written.contains("\r\nConnection: close\r\n"));
+}
+
+/// Private helper to write the response in [version] and read it back.
+fn written(resp: &Response, version: Versions) -> String {
+    let mut stream: MockStream = Cursor::new(Vec::new());
+    resp.respond_as(version, &mut stream).unwrap();
+    String::from_utf8(stream.into_inner()).unwrap()
+}
+
+#[test]
+/// Test streamed bodies of unknown length with chunked transfer-encoding.
+fn respond_chunked() {
+    let chunks = vec![Vec::from("Hello, "), Vec::new(), Vec::from("streamed world!")];
+    let resp = Response::builder()
+        .body_chunks(chunks.into_iter(), None)
+        .trailer("X-Checksum", "42")
+        .build();
+    assert!(resp.get_header("Content-Length").is_none());
+
+    let out = written(&resp, Versions::Http1_1);
+    assert!(out.contains("\r\nTransfer-Encoding: chunked\r\nTrailer: X-Checksum\r\nConnection: close\r\n\r\n"));
+    assert!(out.ends_with("\r\n\r\n7\r\nHello, \r\nF\r\nstreamed world!\r\n0\r\nX-Checksum: 42\r\n\r\n"));
+
+    // A streamed body can only be sent once
+    let mut stream: MockStream = Cursor::new(Vec::new());
+    assert!(resp.respond(&mut stream).is_err());
+
+    // Reader larger than one chunk
+    let data = vec![b'a'; 10_000];
+    let resp = Response::builder()
+        .body_reader(Cursor::new(data), None)
+        .build();
+    let out = written(&resp, Versions::Http1_1);
+    assert!(out.contains("\r\n\r\n2000\r\n"));
+    assert!(out.contains("\r\n710\r\n"));
+    assert!(out.ends_with("\r\n0\r\n\r\n"));
+}
+
+#[test]
+/// Test streamed bodies of known length and streaming to HTTP/1.0 clients.
+fn respond_streamed() {
+    let resp = Response::builder()
+        .body_reader(Cursor::new(Vec::from("Hello, world!")), Some(5))
+        .build();
+    assert!(resp.get_header("Content-Length").unwrap().get_value() == "5");
+    let out = written(&resp, Versions::Http1_1);
+    assert!(!out.contains("Transfer-Encoding"));
+    assert!(out.ends_with("\r\n\r\nHello"));
+
+    // Source longer than promised is cut, even if it never ends
+    let resp = Response::builder()
+        .body_chunks(std::iter::repeat(Vec::from("Meow")), Some(10))
+        .build();
+    let out = written(&resp, Versions::Http1_1);
+    assert!(out.ends_with("\r\n\r\nMeowMeowMe"));
+
+    // Source shorter than promised
+    let resp = Response::builder()
+        .body_chunks(vec![Vec::from("Hi")].into_iter(), Some(5))
+        .build();
+    let mut stream: MockStream = Cursor::new(Vec::new());
+    assert!(resp.respond(&mut stream).is_err());
+
+    // No chunked for HTTP/1.0, the body ends when the connection closes
+    let resp = Response::builder()
+        .body_chunks(vec![Vec::from("Hello, "), Vec::from("world!")].into_iter(), None)
+        .trailer("X-Checksum", "42")
+        .build();
+    let out = written(&resp, Versions::Http1_0);
+    assert!(out.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(!out.contains("Transfer-Encoding"));
+    assert!(!out.contains("X-Checksum"));
+    assert!(out.contains("\r\nConnection: close\r\n"));
+    assert!(out.ends_with("\r\n\r\nHello, world!"));
+}
+
+#[test]
+/// Test [Response] json shortcut.
+fn json() {
+    let value = Value::parse("{\"meow\": [1, 2]}").unwrap();
+    let resp = Response::json(&value);
+
+    assert!(resp.get_header("Content-Type").unwrap().get_value() == "application/json");
+    assert!(written(&resp, Versions::Http1_1).ends_with("\r\n\r\n{\"meow\":[1,2]}"));
+}
+
+#[test]
+/// Test [ResponseBuilder] cookie method.
+fn builder_cookie() {
+    let resp = Response::builder()
+        .cookie(&SetCookie::new("a", "1")).unwrap()
+        .cookie(&SetCookie::new("b", "2").http_only(true)).unwrap()
+        .build();
+    let cookies: Vec<_> = resp.get_headers().iter()
+        .filter(|h| h.get_name() == "Set-Cookie")
+        .map(|h| h.get_value().as_str())
+        .collect();
+    assert!(cookies == vec!["a=1", "b=2; HttpOnly"]);
+
+    assert!(Response::builder().cookie(&SetCookie::new("a", "1;2")).is_err());
+}
@@ -0,0 +1,605 @@
+use std::{
+    collections::{hash_map::RandomState, BTreeMap, HashMap},
+    fmt::Debug,
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{Error, ErrorKind, Read, Write},
+    path::PathBuf,
+    sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use crate::log;
+use crate::web::cookie::{SameSite, SetCookie};
+use crate::web::response::Header;
+use crate::web::uri::percent_decode;
+
+/// Minimal SHA-256 (FIPS 180-4) and HMAC (RFC 2104), enough to sign cookies.
+mod hmac;
+
+/// Number of saves after which a store drops expired sessions.
+const PURGE_INTERVAL: usize = 64;
+
+/// Age after which a temporary file of [FileStore] is left over from an
+/// interrupted save, rather than being written right now.
+const STALE_TEMP_FILE: Duration = Duration::from_secs(60);
+
+/// Stored state of a session: the data and times that its timeouts
+/// are measured from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionRecord {
+    data: BTreeMap<String, String>,
+    created: SystemTime,
+    accessed: SystemTime
+}
+
+impl SessionRecord {
+    pub fn new(data: BTreeMap<String, String>, created: SystemTime, accessed: SystemTime) -> SessionRecord {
+        SessionRecord { data, created, accessed }
+    }
+
+    pub const fn get_data(&self) -> &BTreeMap<String, String> {
+        &self.data
+    }
+
+    pub const fn get_created(&self) -> SystemTime {
+        self.created
+    }
+
+    pub const fn get_accessed(&self) -> SystemTime {
+        self.accessed
+    }
+
+    /// Whether the record is too old for either of the timeouts.
+    fn is_expired(&self, idle_timeout: Duration, absolute_timeout: Duration) -> bool {
+        let elapsed = |since: SystemTime| SystemTime::now().duration_since(since).unwrap_or(Duration::ZERO);
+        elapsed(self.accessed) > idle_timeout || elapsed(self.created) > absolute_timeout
+    }
+}
+
+/// Pluggable storage of [SessionRecord]s by session ID.
+/// Shared between workers, hence [Send] + [Sync].
+pub trait SessionStore: Send + Sync {
+    fn load(&self, id: &str) -> Option<SessionRecord>;
+    fn save(&self, id: &str, record: &SessionRecord) -> Result<(), Error>;
+    fn remove(&self, id: &str);
+}
+
+/// Keeps sessions in memory. Sessions not accessed within [ttl] are
+/// dropped, so abandoned sessions don't pile up.
+/// 
+/// The ttl is independent of the [SessionConfig] timeouts and the shorter
+/// one wins, so it should be at least the idle timeout of the config.
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, SessionRecord>>,
+    ttl: Duration,
+    saves: AtomicUsize
+}
+
+impl MemoryStore {
+    pub fn new(ttl: Duration) -> MemoryStore {
+        MemoryStore {
+            sessions: Mutex::new(HashMap::new()),
+            ttl,
+            saves: AtomicUsize::new(0)
+        }
+    }
+
+    /// Drops all sessions not accessed within ttl.
+    pub fn purge(&self) {
+        self.sessions.lock().unwrap().retain(|_, record| !record.is_expired(self.ttl, Duration::MAX));
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Option<SessionRecord> {
+        let record = self.sessions.lock().unwrap().get(id).cloned()?;
+        if record.is_expired(self.ttl, Duration::MAX) {
+            self.remove(id);
+            return None
+        }
+        Some(record)
+    }
+
+    fn save(&self, id: &str, record: &SessionRecord) -> Result<(), Error> {
+        self.sessions.lock().unwrap().insert(id.to_string(), record.clone());
+        if self.saves.fetch_add(1, Ordering::Relaxed) % PURGE_INTERVAL == PURGE_INTERVAL - 1 {
+            self.purge();
+        }
+        Result::Ok(())
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions.lock().unwrap().remove(id);
+    }
+}
+
+/// Keeps each session in its own file in [dir], so sessions survive a restart.
+/// Sessions not accessed within [ttl] are dropped as in [MemoryStore],
+/// and the same applies to a ttl shorter than the idle timeout.
+/// Files are only readable by the owner of the process on unix.
+/// 
+/// File format is line based: created and accessed times in seconds since
+/// the epoch, followed by "key=value" lines with percent-encoded key and value.
+pub struct FileStore {
+    dir: PathBuf,
+    ttl: Duration,
+    saves: AtomicUsize
+}
+
+impl FileStore {
+    /// Creates [dir] if it does not exist.
+    pub fn new(dir: PathBuf, ttl: Duration) -> Result<FileStore, Error> {
+        fs::create_dir_all(&dir)?;
+        Result::Ok(
+            FileStore {
+                dir,
+                ttl,
+                saves: AtomicUsize::new(0)
+            }
+        )
+    }
+
+    /// Drops all sessions not accessed within ttl, and temporary files
+    /// left over from saves that were interrupted.
+    pub fn purge(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue
+            };
+            if let Some(id) = name.strip_suffix(".session") {
+                // load() removes expired sessions
+                self.load(id);
+            } else if is_temp_file(name) && entry.metadata().and_then(|m| m.modified())
+                .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > STALE_TEMP_FILE)) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    /// Private helper to map an ID to its file. IDs that are not lower case
+    /// hex are refused, so an ID can't name a path outside of [dir].
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        if id.is_empty() || !id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+            return None
+        }
+        Some(self.dir.join(format!("{id}.session")))
+    }
+}
+
+/// Private helper to match the "<id>.tmp<n>" names of [FileStore] temporary files.
+fn is_temp_file(name: &str) -> bool {
+    name.rsplit_once(".tmp")
+        .is_some_and(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Private helper to percent-encode characters that delimit the file format.
+fn encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '%' | '=' | '\n' | '\r' => encoded.push_str(&format!("%{:02X}", c as u32)),
+            c => encoded.push(c)
+        }
+    }
+    encoded
+}
+
+/// Private helper to parse a session file.
+fn decode_record(text: &str) -> Option<SessionRecord> {
+    let mut lines = text.lines();
+    let mut time = || -> Option<SystemTime> {
+        Some(UNIX_EPOCH + Duration::from_secs(lines.next()?.parse().ok()?))
+    };
+    let created = time()?;
+    let accessed = time()?;
+
+    let mut data = BTreeMap::new();
+    for line in lines {
+        let (key, value) = line.split_once('=')?;
+        let key = String::from_utf8(percent_decode(key, false).ok()?).ok()?;
+        let value = String::from_utf8(percent_decode(value, false).ok()?).ok()?;
+        data.insert(key, value);
+    }
+
+    Some(SessionRecord { data, created, accessed })
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> Option<SessionRecord> {
+        let path = self.path(id)?;
+        let text = fs::read_to_string(&path).ok()?;
+
+        match decode_record(&text) {
+            Some(record) if !record.is_expired(self.ttl, Duration::MAX) => Some(record),
+            // Expired or corrupt
+            _ => {
+                let _ = fs::remove_file(path);
+                None
+            }
+        }
+    }
+
+    fn save(&self, id: &str, record: &SessionRecord) -> Result<(), Error> {
+        let path = match self.path(id) {
+            Some(path) => path,
+            None => return Result::Err(
+                Error::new(ErrorKind::InvalidInput, "session id is not hex")
+            )
+        };
+
+        let secs = |time: SystemTime| time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        let mut text = format!("{}\n{}\n", secs(record.created), secs(record.accessed));
+        for (key, value) in &record.data {
+            text.push_str(&format!("{}={}\n", encode(key), encode(value)));
+        }
+
+        // Write a whole file and rename, so a concurrent load never sees half of it
+        let save = self.saves.fetch_add(1, Ordering::Relaxed);
+        let temp_path = path.with_extension(format!("tmp{save}"));
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        // Session data is private to the server
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options.open(&temp_path)?.write_all(text.as_bytes())?;
+        fs::rename(&temp_path, &path)?;
+
+        if save % PURGE_INTERVAL == PURGE_INTERVAL - 1 {
+            self.purge();
+        }
+        Result::Ok(())
+    }
+
+    fn remove(&self, id: &str) {
+        if let Some(path) = self.path(id) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Generates a new random session ID of 64 hex characters.
+/// Uses the OS randomness where available, and a seed from the randomly
+/// keyed std hasher otherwise.
+fn generate_id() -> String {
+    let mut bytes = [0u8; 32];
+
+    let from_os = File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes));
+    if from_os.is_err() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+
+        for chunk in bytes.chunks_mut(8) {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_usize(COUNTER.fetch_add(1, Ordering::Relaxed));
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+
+    hmac::to_hex(&bytes)
+}
+
+/// Session middleware settings. Each client is given a session ID in a cookie
+/// signed with HMAC-SHA256, so IDs can't be forged or guessed from a store.
+/// A session ends after [idle_timeout] without requests, or [absolute_timeout]
+/// after it started, whichever comes first. A store ttl shorter than
+/// [idle_timeout] ends idle sessions earlier, since the store drops them.
+/// 
+/// # Example
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// 
+/// use rns::web::session::{MemoryStore, SessionConfig};
+/// 
+/// let store = Arc::new(MemoryStore::new(Duration::from_secs(30 * 60)));
+/// let config = SessionConfig::new(store, b"a secret key of at least 32 bytes!")
+///     .cookie_name("sid")
+///     .idle_timeout(Duration::from_secs(30 * 60));
+/// ```
+pub struct SessionConfig {
+    store: Arc<dyn SessionStore>,
+    key: Vec<u8>,
+    cookie_name: String,
+    cookie_path: String,
+    secure: bool,
+    same_site: SameSite,
+    idle_timeout: Duration,
+    absolute_timeout: Duration
+}
+
+impl SessionConfig {
+    /// # Parameters
+    /// store - where session data is kept.
+    /// key - secret used to sign session cookies.
+    /// 
+    /// # Panics
+    /// Will panic if key is shorter than 32 bytes.
+    pub fn new(store: Arc<dyn SessionStore>, key: &[u8]) -> SessionConfig {
+        assert!(key.len() >= 32, "session key must be at least 32 bytes");
+
+        SessionConfig {
+            store,
+            key: key.to_vec(),
+            cookie_name: "rns_session".to_string(),
+            cookie_path: "/".to_string(),
+            secure: false,
+            same_site: SameSite::Lax,
+            idle_timeout: Duration::from_secs(30 * 60),
+            absolute_timeout: Duration::from_secs(24 * 60 * 60)
+        }
+    }
+
+    pub fn cookie_name(mut self, name: &str) -> SessionConfig {
+        self.cookie_name = name.to_string();
+        self
+    }
+
+    pub fn cookie_path(mut self, path: &str) -> SessionConfig {
+        self.cookie_path = path.to_string();
+        self
+    }
+
+    /// Sets the Secure attribute, should be true behind HTTPS.
+    pub fn secure(mut self, secure: bool) -> SessionConfig {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> SessionConfig {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Should not exceed the ttl of the store, which drops the session first.
+    pub fn idle_timeout(mut self, timeout: Duration) -> SessionConfig {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    pub fn absolute_timeout(mut self, timeout: Duration) -> SessionConfig {
+        self.absolute_timeout = timeout;
+        self
+    }
+
+    pub fn get_cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+
+    /// Private helper to produce "<id>.<hex signature>".
+    fn sign(&self, id: &str) -> String {
+        format!("{id}.{}", hmac::to_hex(&hmac::hmac_sha256(&self.key, id.as_bytes())))
+    }
+
+    /// Private helper to return the ID of a correctly signed cookie value.
+    fn verify<'a>(&self, cookie_value: &'a str) -> Option<&'a str> {
+        let (id, _) = cookie_value.split_once('.')?;
+        hmac::constant_time_eq(self.sign(id).as_bytes(), cookie_value.as_bytes()).then_some(id)
+    }
+
+    /// Private helper to build the session cookie, or its removal.
+    fn cookie(&self, value: Option<&str>) -> SetCookie {
+        let cookie = match value {
+            Some(value) => SetCookie::new(&self.cookie_name, value),
+            None => SetCookie::removal(&self.cookie_name)
+        };
+        cookie
+            .path(&self.cookie_path)
+            .secure(self.secure)
+            .http_only(true)
+            .same_site(self.same_site)
+    }
+
+    /// Starts the session of a request given its session cookie value.
+    /// A missing, forged or expired session starts a new one.
+    pub(crate) fn start(self: &Arc<Self>, cookie_value: Option<&str>) -> Session {
+        let loaded = cookie_value
+            .and_then(|value| self.verify(value))
+            .and_then(|id| Some((id.to_string(), self.store.load(id)?)));
+
+        match loaded {
+            Some((id, record)) if !record.is_expired(self.idle_timeout, self.absolute_timeout) => {
+                Session {
+                    id,
+                    record,
+                    config: self.clone(),
+                    state: SessionState::Existing,
+                    stale_id: None,
+                    written: false,
+                    issued_id: None
+                }
+            }
+            loaded => {
+                if let Some((id, _)) = loaded {
+                    self.store.remove(&id);
+                }
+                let now = SystemTime::now();
+                Session {
+                    id: generate_id(),
+                    record: SessionRecord::new(BTreeMap::new(), now, now),
+                    config: self.clone(),
+                    state: SessionState::New,
+                    stale_id: None,
+                    written: false,
+                    issued_id: None
+                }
+            }
+        }
+    }
+}
+
+/// Whether the client needs a new cookie for the [Session].
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SessionState {
+    Existing,
+    New,
+    Destroyed
+}
+
+/// Session of the client, exposed to handlers as a key/value map.
+/// Changes are saved to the store after the handler returns.
+/// A new session is saved and its cookie is sent only once it was written to,
+/// so clients without a cookie don't fill the store. A new or rotated session
+/// written to after the response was sent is not saved, as the client never
+/// got its cookie.
+/// 
+/// # Example
+/// ```
+/// use rns::web::request::Request;
+/// 
+/// fn login(request: &mut Request) {
+///     if let Some(session) = request.get_session_mut() {
+///         // New ID after privilege change prevents session fixation
+///         session.rotate();
+///         session.insert("user", "neko");
+///     }
+/// }
+/// ```
+pub struct Session {
+    id: String,
+    record: SessionRecord,
+    config: Arc<SessionConfig>,
+    state: SessionState,
+    stale_id: Option<String>,
+    written: bool,
+    /// ID the client was sent a cookie for.
+    issued_id: Option<String>
+}
+
+/// ID and data are left out, since they are secrets that could end up in logs.
+impl Debug for Session {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Session")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Session {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether the session started with this request.
+    pub fn is_new(&self) -> bool {
+        self.state == SessionState::New
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.record.data.get(key)
+    }
+
+    pub fn insert(&mut self, key: &str, value: &str) -> Option<String> {
+        self.written = true;
+        self.record.data.insert(key.to_string(), value.to_string())
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.written = true;
+        self.record.data.remove(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.written = true;
+        self.record.data.clear();
+    }
+
+    pub const fn get_data(&self) -> &BTreeMap<String, String> {
+        &self.record.data
+    }
+
+    /// Moves the data to a new ID and invalidates the old one.
+    /// Call on login and other privilege changes.
+    pub fn rotate(&mut self) {
+        if self.state == SessionState::Existing {
+            self.stale_id.get_or_insert(self.id.clone());
+        }
+        self.id = generate_id();
+        self.state = SessionState::New;
+        self.written = true;
+    }
+
+    /// Ends the session: data is removed and the client's cookie is cleared.
+    pub fn destroy(&mut self) {
+        if self.state == SessionState::Existing {
+            self.stale_id.get_or_insert(self.id.clone());
+        }
+        self.record.data.clear();
+        self.state = SessionState::Destroyed;
+    }
+
+    /// Whether a new session was left untouched and can be dropped.
+    fn is_unused(&self) -> bool {
+        self.state == SessionState::New && !self.written
+    }
+
+    /// The Set-Cookie header the response must carry, if any.
+    fn cookie_header(&self) -> Option<Header> {
+        if self.is_unused() {
+            return None
+        }
+
+        let cookie = match self.state {
+            SessionState::Existing => return None,
+            SessionState::New => self.config.cookie(Some(&self.config.sign(&self.id))),
+            SessionState::Destroyed => self.config.cookie(None)
+        };
+        // Session IDs are hex and the rest is configuration
+        cookie.build().ok()
+    }
+
+    /// The Set-Cookie header for the response being sent, if any.
+    /// Records the ID the client will hold, which [persist] requires
+    /// before it saves a new session.
+    pub(crate) fn issue_cookie(&mut self) -> Option<Header> {
+        let header = self.cookie_header();
+        if header.is_some() && self.state == SessionState::New {
+            self.issued_id = Some(self.id.clone());
+        }
+        header
+    }
+
+    /// Writes changes to the store, the end of the request counts as an access.
+    /// A new session is not saved unless its cookie was issued.
+    pub(crate) fn persist(mut self) -> Result<(), Error> {
+        let store = &self.config.store;
+        if let Some(stale_id) = &self.stale_id {
+            store.remove(stale_id);
+        }
+
+        if self.state == SessionState::New && self.issued_id.as_ref() != Some(&self.id) {
+            if !self.is_unused() {
+                log::warn(module_path!(), "session written after the response was sent, not saved", &[]);
+            }
+            return Result::Ok(())
+        }
+
+        match self.state {
+            SessionState::Destroyed => {
+                store.remove(&self.id);
+                Result::Ok(())
+            }
+            _ => {
+                self.record.accessed = SystemTime::now();
+                store.save(&self.id, &self.record)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
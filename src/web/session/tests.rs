@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, env, fs, io::Cursor, sync::Arc, thread, time::{Duration, SystemTime}};
+
+use crate::web::request::RequestBackend;
+use crate::web::session::{FileStore, MemoryStore, SessionConfig, SessionRecord, SessionStore};
+
+const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+fn config(store: Arc<dyn SessionStore>) -> Arc<SessionConfig> {
+    Arc::new(SessionConfig::new(store, KEY))
+}
+
+/// Returns the signed cookie value a new [Session](super::Session) was issued with.
+fn issued_value(header_value: &str) -> String {
+    let pair = header_value.split(';').next().unwrap();
+    pair.split_once('=').unwrap().1.to_string()
+}
+
+#[test]
+/// Test that only values signed with the key are accepted.
+fn sign_verify() {
+    let config = config(Arc::new(MemoryStore::new(Duration::from_secs(60))));
+    let signed = config.sign("abcd");
+
+    assert!(config.verify(&signed) == Some("abcd"));
+    assert!(config.verify("abcd").is_none());
+    assert!(config.verify("abce.".to_string().as_str()).is_none());
+    assert!(config.verify(&signed.replacen("abcd", "abce", 1)).is_none());
+
+    let other = SessionConfig::new(Arc::new(MemoryStore::new(Duration::from_secs(60))), &[7; 32]);
+    assert!(other.verify(&signed).is_none());
+}
+
+#[test]
+#[should_panic]
+/// Test that short keys are refused.
+fn short_key() {
+    SessionConfig::new(Arc::new(MemoryStore::new(Duration::from_secs(60))), b"short");
+}
+
+#[test]
+/// Test a session round trip through a [MemoryStore].
+fn memory_round_trip() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(60)));
+    let config = config(store.clone());
+
+    let mut session = config.start(None);
+    assert!(session.is_new());
+    session.insert("user", "neko");
+
+    let header = session.issue_cookie().unwrap();
+    assert!(header.get_value().contains("HttpOnly"));
+    assert!(header.get_value().contains("SameSite=Lax"));
+    let value = issued_value(header.get_value());
+    session.persist().unwrap();
+    assert!(store.len() == 1);
+
+    let session = config.start(Some(&value));
+    assert!(!session.is_new());
+    assert!(session.get("user").is_some_and(|v| v == "neko"));
+    assert!(session.cookie_header().is_none());
+
+    // Forged or unknown cookies start over
+    let forged = format!("{}.{}", "ab".repeat(16), "00".repeat(32));
+    assert!(config.start(Some(&forged)).is_new());
+    assert!(config.start(Some("garbage")).is_new());
+}
+
+#[test]
+/// Test idle and absolute expiry.
+fn expiry() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(3600)));
+    let config = Arc::new(
+        SessionConfig::new(store.clone(), KEY).idle_timeout(Duration::from_secs(60))
+    );
+    let now = SystemTime::now();
+
+    store.save("aa", &SessionRecord::new(BTreeMap::new(), now, now - Duration::from_secs(120))).unwrap();
+    assert!(config.start(Some(&config.sign("aa"))).is_new());
+    assert!(store.load("aa").is_none(), "expired session should be removed");
+
+    let config = Arc::new(
+        SessionConfig::new(store.clone(), KEY).absolute_timeout(Duration::from_secs(60))
+    );
+    store.save("bb", &SessionRecord::new(BTreeMap::new(), now - Duration::from_secs(120), now)).unwrap();
+    assert!(config.start(Some(&config.sign("bb"))).is_new());
+
+    store.save("cc", &SessionRecord::new(BTreeMap::new(), now, now)).unwrap();
+    assert!(!config.start(Some(&config.sign("cc"))).is_new());
+}
+
+#[test]
+/// Test that rotate moves the data and drops the old ID, and destroy clears the cookie.
+fn rotate_destroy() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(60)));
+    let config = config(store.clone());
+
+    let mut session = config.start(None);
+    session.insert("cart", "3");
+    let old_id = session.get_id().to_string();
+    let value = issued_value(session.issue_cookie().unwrap().get_value());
+    session.persist().unwrap();
+
+    let mut session = config.start(Some(&value));
+    session.rotate();
+    let new_id = session.get_id().to_string();
+    assert!(new_id != old_id);
+    assert!(session.issue_cookie().is_some());
+    session.persist().unwrap();
+
+    assert!(store.load(&old_id).is_none());
+    assert!(store.load(&new_id).is_some_and(|r| r.get_data().get("cart").is_some_and(|v| v == "3")));
+    assert!(config.start(Some(&value)).is_new());
+
+    let mut session = config.start(Some(&config.sign(&new_id)));
+    session.destroy();
+    let header = session.cookie_header().unwrap();
+    assert!(header.get_value().starts_with("rns_session=;"));
+    assert!(header.get_value().contains("Max-Age=0"));
+    session.persist().unwrap();
+    assert!(store.is_empty());
+}
+
+#[test]
+/// Test a [FileStore] round trip, including keys and values that need escaping.
+fn file_store() {
+    let dir = env::temp_dir().join(format!("rns-sessions-{}-{:?}", std::process::id(), thread::current().id()));
+    let store = FileStore::new(dir.clone(), Duration::from_secs(60)).unwrap();
+
+    let mut data = BTreeMap::new();
+    data.insert("user name".to_string(), "a=b&c\n%".to_string());
+    data.insert("empty".to_string(), String::new());
+    let now = SystemTime::now();
+    store.save("0a1b", &SessionRecord::new(data.clone(), now, now)).unwrap();
+
+    let record = store.load("0a1b").unwrap();
+    assert!(*record.get_data() == data);
+
+    // Non-hex IDs never touch the filesystem
+    assert!(store.load("../0a1b").is_none());
+    assert!(store.save("../x", &record).is_err());
+
+    store.remove("0a1b");
+    assert!(store.load("0a1b").is_none());
+
+    // Expired by the store's own TTL
+    store.save("cc", &SessionRecord::new(BTreeMap::new(), now, now - Duration::from_secs(120))).unwrap();
+    store.purge();
+    assert!(store.load("cc").is_none());
+
+    // Files are private to the server
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        store.save("dd", &record).unwrap();
+        let mode = dir.join("dd.session").metadata().unwrap().permissions().mode();
+        assert!(mode & 0o777 == 0o600, "session file must be private, got {mode:o}");
+    }
+
+    // Leftovers of interrupted saves are purged, saves in progress are not
+    let stale = dir.join("ee.tmp3");
+    fs::File::create(&stale).unwrap().set_modified(now - Duration::from_secs(3600)).unwrap();
+    let fresh = dir.join("ff.tmp4");
+    fs::File::create(&fresh).unwrap();
+    store.purge();
+    assert!(!stale.exists());
+    assert!(fresh.exists());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+/// Test that a new session is neither saved nor sent until it is written to.
+fn unused_session() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(60)));
+    let config = config(store.clone());
+
+    let session = config.start(None);
+    assert!(session.get("user").is_none());
+    assert!(session.cookie_header().is_none());
+    session.persist().unwrap();
+    assert!(store.is_empty());
+
+    let mut session = config.start(None);
+    session.remove("user");
+    assert!(session.issue_cookie().is_some());
+    session.persist().unwrap();
+    assert!(store.len() == 1);
+}
+
+#[test]
+/// Test that a session written after its response is not saved without a cookie.
+fn written_after_respond() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(60)));
+    let config = config(store.clone());
+
+    let mut session = config.start(None);
+    assert!(session.issue_cookie().is_none());
+    session.insert("user", "neko");
+    session.persist().unwrap();
+    assert!(store.is_empty());
+
+    // Rotated after the cookie of the old ID went out
+    let mut session = config.start(None);
+    session.insert("user", "neko");
+    assert!(session.issue_cookie().is_some());
+    session.rotate();
+    session.persist().unwrap();
+    assert!(store.is_empty());
+}
+
+#[test]
+/// Test that [RequestBackend] respond adds the cookie of a new session.
+fn respond_with_cookie() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(60)));
+    let config = config(store.clone());
+    let request_str = "GET / HTTP/1.1\r\n\r\n";
+
+    let stream = Cursor::new(Vec::from(request_str.as_bytes()));
+    let mut req = RequestBackend::build(stream).unwrap();
+    let mut session = config.start(None);
+    session.insert("user", "neko");
+    req.set_session(session);
+    req.respond_code(crate::web::response::ResponseCode::get_204()).unwrap();
+
+    let written = String::from_utf8(req.get_response_stream().get_ref()[request_str.len()..].to_vec()).unwrap();
+    assert!(written.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(written.contains("\r\nSet-Cookie: rns_session="));
+
+    let session = req.take_session().unwrap();
+    assert!(req.get_session().is_none());
+    session.persist().unwrap();
+    assert!(store.len() == 1);
+}
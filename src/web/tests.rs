@@ -1,4 +1,10 @@
 use super::*;
+use std::io::{Read, Write};
+use std::thread;
+use std::time::Duration;
+
+use crate::web::session::MemoryStore;
+use crate::web::uri::Target;
 
 #[test]
 /// Test [RouteMap] with one method and one uri per action.
@@ -7,10 +13,10 @@ use super::*;
 fn route_map_single() {
     let mut route_map = RouteMap::new();
 
-    fn dummy_function(_request: Request) {}
+    fn dummy_function(_request: &mut Request) {}
 
     let fn_ptr = Arc::new(dummy_function);
-    let cl_ptr = Arc::new(|_request: Request| {});
+    let cl_ptr = Arc::new(|_request: &mut Request| {});
 
     let uri_1 = "/test-fn".to_string();
     let uri_2 = "/test-cl".to_string();
@@ -42,7 +48,7 @@ fn route_map_single() {
 fn route_map_multi() {
     let mut route_map = RouteMap::new();
 
-    fn dummy_function(_request: Request) {}
+    fn dummy_function(_request: &mut Request) {}
 
     let fn_ptr = Arc::new(dummy_function);
     let uri = "/test-fn".to_string();
@@ -68,7 +74,7 @@ fn route_map_multi() {
 fn route_map_negative() {
     let mut route_map = RouteMap::new();
 
-    fn dummy_function(_request: Request) {}
+    fn dummy_function(_request: &mut Request) {}
 
     let fn_ptr = Arc::new(dummy_function);
     let uri = "/test-fn".to_string();
@@ -88,7 +94,7 @@ fn route_map_negative() {
         &"GET".to_string()
     );
     match return_404 {
-        Ok(_) => assert!(false, "should be 404 Not Found"),
+        Ok(_) => panic!("should be 404 Not Found"),
         Err(code) => assert!(
             code == ResponseCode::get_404(),
             "should be 404 Not Found"
@@ -101,10 +107,205 @@ fn route_map_negative() {
         &"POST".to_string()
     );
     match return_405 {
-        Ok(_) => assert!(false, "should be 405 Method Not Allowed"),
+        Ok(_) => panic!("should be 405 Method Not Allowed"),
         Err(code) => assert!(
             code == ResponseCode::get_405(),
             "should be 405 Method Not Allowed"
         )
     }
 }
+
+#[test]
+/// Test that [PooledServer] answers a client that stalls mid-request with 408.
+fn serve_request_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all("GET /test HTTP/1.1\r\nHost: exa".as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+
+    let (socket, _) = listener.accept().unwrap();
+    let timeouts = Timeouts::default().header(Duration::from_millis(100));
+    let settings = Arc::new(Settings { timeouts, ..Settings::new(RouteMap::new()) });
+    <PooledServer as ServerBackend>::serve_request(socket, settings);
+
+    let response = client.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+}
+
+#[test]
+/// Test [RouteMap] lookups with paths normalised by a [Request].
+fn route_map_normalized() {
+    let mut route_map = RouteMap::new();
+
+    fn dummy_function(_request: &mut Request) {}
+
+    route_map.insert_route("/a b".to_string(), "GET".to_string(), Arc::new(dummy_function));
+    route_map.insert_route("/x%2Fy".to_string(), "GET".to_string(), Arc::new(dummy_function));
+
+    for path in ["/a%20b", "/x/../a%20b", "//a b"] {
+        let target = Target::parse(path).unwrap();
+        route_map.get_action(target.get_path(), &"GET".to_string()).unwrap();
+    }
+
+    let target = Target::parse("/x/y").unwrap();
+    assert!(route_map.get_action(target.get_path(), &"GET".to_string()).is_err());
+    let target = Target::parse("/x%2fy").unwrap();
+    route_map.get_action(target.get_path(), &"GET".to_string()).unwrap();
+}
+
+#[test]
+/// Test the methods [RouteMap] allows without a registered route.
+fn route_map_implied_methods() {
+    let mut route_map = RouteMap::new();
+
+    fn dummy_function(_request: &mut Request) {}
+
+    route_map.insert_route("/get".to_string(), "GET".to_string(), Arc::new(dummy_function));
+    route_map.insert_route("/post".to_string(), "POST".to_string(), Arc::new(dummy_function));
+
+    assert!(route_map.get_methods(&"/get".to_string()).unwrap() == vec!["GET", "HEAD", "OPTIONS"]);
+    assert!(route_map.get_methods(&"/post".to_string()).unwrap() == vec!["OPTIONS", "POST"]);
+    assert!(route_map.get_methods(&"/none".to_string()).is_none());
+
+    assert!(route_map.get_action(&"/get".to_string(), &"HEAD".to_string()).is_ok());
+    assert!(route_map.get_action(&"/post".to_string(), &"OPTIONS".to_string()).is_ok());
+    assert!(route_map.get_action(&"/post".to_string(), &"HEAD".to_string()).is_err_and(
+        |code| code == ResponseCode::get_405()
+    ));
+}
+
+/// Sends [request_str] to [PooledServer] serve_request and returns the response.
+fn served(route_map: RouteMap, request_str: &'static str) -> String {
+    served_with(Settings::new(route_map), request_str)
+}
+
+/// Same as [served], but with the given [Settings].
+fn served_with(settings: Settings, request_str: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request_str.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+
+    let (socket, _) = listener.accept().unwrap();
+    <PooledServer as ServerBackend>::serve_request(socket, Arc::new(settings));
+
+    client.join().unwrap()
+}
+
+#[test]
+/// Test the Allow header of 405 and OPTIONS, and HEAD answered by the GET route.
+fn serve_request_methods() {
+    let route_map = || {
+        let mut route_map = RouteMap::new();
+        route_map.insert_route(
+            "/test".to_string(),
+            "GET".to_string(),
+            Arc::new(|request: &mut Request| {
+                request.respond(&Response::text("hello")).unwrap();
+            })
+        );
+        route_map
+    };
+
+    let response = served(route_map(), "DELETE /test HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    assert!(response.contains("\r\nAllow: GET, HEAD, OPTIONS\r\n"));
+
+    let response = served(route_map(), "OPTIONS /test HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(response.contains("\r\nAllow: GET, HEAD, OPTIONS\r\n"));
+
+    let response = served(route_map(), "HEAD /test HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("\r\nContent-Length: 5\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+}
+
+#[test]
+/// Test that a request without a session cookie only gets a session once it is written to.
+fn serve_request_sessions() {
+    let store = Arc::new(MemoryStore::new(Duration::from_secs(60)));
+    let settings = || {
+        let mut route_map = RouteMap::new();
+        route_map.insert_route(
+            "/read".to_string(),
+            "GET".to_string(),
+            Arc::new(|request: &mut Request| {
+                let user = request.get_session().and_then(|s| s.get("user").cloned());
+                request.respond(&Response::text(&user.unwrap_or_default())).unwrap();
+            })
+        );
+        route_map.insert_route(
+            "/login".to_string(),
+            "POST".to_string(),
+            Arc::new(|request: &mut Request| {
+                request.get_session_mut().unwrap().insert("user", "neko");
+                request.respond_code(ResponseCode::get_204()).unwrap();
+            })
+        );
+        Settings {
+            sessions: Some(Arc::new(SessionConfig::new(store.clone(), b"0123456789abcdef0123456789abcdef"))),
+            ..Settings::new(route_map)
+        }
+    };
+
+    let response = served_with(settings(), "GET /read HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(!response.contains("\r\nSet-Cookie"));
+    assert!(store.is_empty(), "unused session must not be saved");
+
+    let response = served_with(settings(), "POST /login HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(response.contains("\r\nSet-Cookie: rns_session="));
+    assert!(store.len() == 1);
+}
+
+#[test]
+/// Test that a panicking handler is answered with 500.
+fn serve_request_panic() {
+    let mut route_map = RouteMap::new();
+    route_map.insert_route(
+        "/panic".to_string(),
+        "GET".to_string(),
+        Arc::new(|_request: &mut Request| panic!("buggy handler"))
+    );
+
+    let response = served(route_map, "GET /panic HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+}
+
+#[test]
+/// Test that a connection refused by a full pool is answered with 503.
+fn serve_overloaded() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+
+    let (socket, _) = listener.accept().unwrap();
+    let settings = Arc::new(Settings::new(RouteMap::new()));
+    <PooledServer as ServerBackend>::serve_overloaded(socket, settings);
+
+    let response = client.join().unwrap();
+    assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+    assert!(response.contains("\r\nRetry-After: 1\r\n"));
+}
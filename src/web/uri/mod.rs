@@ -0,0 +1,221 @@
+use crate::web::response::{ResponseCode, WebResult};
+
+/// A parsed request target as seen in the status line.
+/// The raw target is kept as is, while [path] is normalised for routing:
+/// 1. percent-escapes are decoded, except for an encoded "/" and "%" which are
+///    kept as "%2F" and "%25" so they never merge with path separators;
+/// 2. empty and "." segments are removed;
+/// 3. ".." removes the previous segment and may not climb above the root.
+/// 
+/// # Example
+/// ```
+/// use rns::web::uri::Target;
+/// 
+/// let target = Target::parse("//static/./img/../a%20b.png?size=2").unwrap();
+/// 
+/// assert!(target.get_path() == "/static/a b.png");
+/// assert!(target.get_query() == Some("size=2"));
+/// ```
+#[derive(Debug)]
+pub struct Target {
+    raw: String,
+    path: String,
+    query: Option<String>
+}
+
+impl Target {
+    /// Parses the origin ("/path?query"), absolute ("http://host/path")
+    /// or asterisk ("*") form of the request target.
+    /// Returns [ResponseCode] of 400 (HTTP 400) on malformed escapes,
+    /// escapes that are not UTF-8 or a path that climbs above the root.
+    pub fn parse(raw: &str) -> WebResult<Target> {
+        if raw == "*" {
+            return Result::Ok(
+                Target {
+                    raw: raw.to_string(),
+                    path: raw.to_string(),
+                    query: None
+                }
+            )
+        }
+
+        // Absolute form: drop the scheme and authority
+        let origin = match raw.split_once("://") {
+            Some((scheme, rest)) if scheme.eq_ignore_ascii_case("http")
+                || scheme.eq_ignore_ascii_case("https") => {
+                match rest.find('/') {
+                    Some(index) => &rest[index..],
+                    None => "/"
+                }
+            }
+            _ => raw
+        };
+
+        // Fragments are never sent by clients, ignore them if one is
+        let origin = match origin.split_once('#') {
+            Some((origin, _)) => origin,
+            None => origin
+        };
+
+        let (path, query) = match origin.split_once('?') {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (origin, None)
+        };
+
+        Result::Ok(
+            Target {
+                raw: raw.to_string(),
+                path: normalize_path(path)?,
+                query
+            }
+        )
+    }
+
+    pub const fn get_raw(&self) -> &String {
+        &self.raw
+    }
+
+    pub const fn get_path(&self) -> &String {
+        &self.path
+    }
+
+    pub fn get_query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+}
+
+/// Normalises a path as described in [Target]. The result is stable,
+/// normalising it again yields the same string.
+/// Returns [ResponseCode] of 400 (HTTP 400) if path is not absolute.
+pub fn normalize_path(path: &str) -> WebResult<String> {
+    if !path.starts_with('/') {
+        return Result::Err(
+            ResponseCode::get_400()
+        )
+    }
+
+    let mut segments: Vec<String> = Vec::new();
+    // "/a/" and "/a/." name a directory, keep the trailing slash for them
+    let mut trailing_slash = false;
+
+    for raw_segment in path.split('/').skip(1) {
+        let decoded = percent_decode(raw_segment, false)?;
+        let segment = match String::from_utf8(decoded) {
+            Ok(s) => s,
+            Err(_) => return Result::Err(
+                ResponseCode::get_400()
+            )
+        };
+
+        if segment.contains('\0') {
+            return Result::Err(
+                ResponseCode::get_400()
+            )
+        }
+
+        trailing_slash = true;
+        match segment.as_str() {
+            "" | "." => {}
+            ".." => {
+                // Climbing above the root is a traversal attempt
+                if segments.pop().is_none() {
+                    return Result::Err(
+                        ResponseCode::get_400()
+                    )
+                }
+            }
+            _ => {
+                trailing_slash = false;
+                segments.push(
+                    segment.replace('%', "%25").replace('/', "%2F")
+                );
+            }
+        }
+    }
+
+    let mut normalized = String::with_capacity(path.len());
+    for segment in &segments {
+        normalized.push('/');
+        normalized.push_str(segment);
+    }
+    if trailing_slash || normalized.is_empty() {
+        normalized.push('/');
+    }
+
+    Result::Ok(normalized)
+}
+
+/// Decodes "%XX" escapes into raw bytes.
+/// Returns [ResponseCode] of 400 (HTTP 400) on a malformed escape.
+/// 
+/// # Parameters
+/// input - an encoded string.
+/// plus_as_space - decode "+" as " ", as done in form bodies and query strings.
+pub fn percent_decode(input: &str, plus_as_space: bool) -> WebResult<Vec<u8>> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let high = bytes.get(i + 1).and_then(|b| (*b as char).to_digit(16));
+                let low = bytes.get(i + 2).and_then(|b| (*b as char).to_digit(16));
+                match (high, low) {
+                    (Some(high), Some(low)) => decoded.push((high * 16 + low) as u8),
+                    _ => return Result::Err(
+                        ResponseCode::get_400()
+                    )
+                }
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    Result::Ok(decoded)
+}
+
+/// Parses "application/x-www-form-urlencoded" data, as found in form bodies
+/// and query strings. Pairs keep their order and repeated keys are kept.
+/// A pair without "=" has an empty value.
+/// Returns [ResponseCode] of 400 (HTTP 400) on a malformed escape or
+/// a decoded name or value that is not UTF-8.
+/// 
+/// # Example
+/// ```
+/// use rns::web::uri::parse_urlencoded;
+/// 
+/// let pairs = parse_urlencoded("tag=a+cat&tag=%F0%9F%90%88&empty").unwrap();
+/// 
+/// assert!(pairs[0] == ("tag".to_string(), "a cat".to_string()));
+/// assert!(pairs[1] == ("tag".to_string(), "🐈".to_string()));
+/// assert!(pairs[2] == ("empty".to_string(), "".to_string()));
+/// ```
+pub fn parse_urlencoded(input: &str) -> WebResult<Vec<(String, String)>> {
+    let decode = |part: &str| match String::from_utf8(percent_decode(part, true)?) {
+        Ok(s) => Result::Ok(s),
+        Err(_) => Result::Err(
+            ResponseCode::get_400()
+        )
+    };
+
+    input
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Result::Ok((decode(name)?, decode(value)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,105 @@
+use crate::web::{response::ResponseCode, uri::{normalize_path, parse_urlencoded, percent_decode, Target}};
+
+#[test]
+/// Test [normalize_path] with dot segments, repeated slashes and escapes.
+fn normalize() {
+    let cases = [
+        ("/", "/"),
+        ("/a%20b", "/a b"),
+        ("/a/./b", "/a/b"),
+        ("//a", "/a"),
+        ("/a/../b", "/b"),
+        ("/a/b/", "/a/b/"),
+        ("/a/b/..", "/a/"),
+        ("/a/%2e%2E/", "/"),
+        ("/a/%2e%2e/b", "/b"),
+        ("/caf%C3%A9", "/café")
+    ];
+
+    for (path, expected) in cases {
+        let normalized = normalize_path(path).unwrap();
+        assert!(normalized == expected, "{path} normalised to {normalized}");
+        assert!(normalize_path(&normalized).unwrap() == normalized, "{path} must be stable");
+    }
+}
+
+#[test]
+/// Test that encoded "/" and "%" stay distinct from the decoded ones.
+fn normalize_encoded_separator() {
+    assert!(normalize_path("/a%2Fb").unwrap() == "/a%2Fb");
+    assert!(normalize_path("/a%2fb").unwrap() == "/a%2Fb");
+    assert!(normalize_path("/a%2Fb").unwrap() != normalize_path("/a/b").unwrap());
+    assert!(normalize_path("/100%25").unwrap() == "/100%25");
+    assert!(normalize_path("/a%252Fb").unwrap() == "/a%252Fb");
+}
+
+#[test]
+/// Test [normalize_path] with Bad Requests.
+fn normalize_fail() {
+    let cases = [
+        "/..",
+        "/a/../..",
+        "/%2e%2e/etc/passwd",
+        "/a/%2E%2E/%2E%2E/etc",
+        "/a%zz",
+        "/a%2",
+        "/%FF",
+        "/a%00b",
+        "relative"
+    ];
+
+    for path in cases {
+        let code = normalize_path(path).unwrap_err();
+        assert!(code == ResponseCode::get_400(), "{path} must be rejected");
+    }
+}
+
+#[test]
+/// Test [Target] parse method with query and various target forms.
+fn parse_target() {
+    let target = Target::parse("/a/./b%20c?x=1&y=%20").unwrap();
+    assert!(target.get_raw() == "/a/./b%20c?x=1&y=%20");
+    assert!(target.get_path() == "/a/b c");
+    assert!(target.get_query() == Some("x=1&y=%20"));
+
+    let target = Target::parse("http://www.example.com/a//b").unwrap();
+    assert!(target.get_path() == "/a/b");
+    assert!(target.get_query().is_none());
+
+    let target = Target::parse("http://www.example.com").unwrap();
+    assert!(target.get_path() == "/");
+
+    let target = Target::parse("*").unwrap();
+    assert!(target.get_path() == "*");
+}
+
+#[test]
+/// Test [percent_decode] with and without "+" as space.
+fn decode() {
+    assert!(percent_decode("a+b%21", false).unwrap() == b"a+b!");
+    assert!(percent_decode("a+b%21", true).unwrap() == b"a b!");
+    assert!(percent_decode("%", true).unwrap_err() == ResponseCode::get_400());
+}
+
+#[test]
+/// Test [parse_urlencoded] with repeated keys, "+" and escapes.
+fn urlencoded() {
+    let pairs = parse_urlencoded("name=Neko+Chan&tag=a&&tag=b%26c&flag&=x&e%3D=1%2B1").unwrap();
+    let expected = [
+        ("name", "Neko Chan"),
+        ("tag", "a"),
+        ("tag", "b&c"),
+        ("flag", ""),
+        ("", "x"),
+        ("e=", "1+1")
+    ];
+
+    assert!(pairs.len() == expected.len());
+    for ((name, value), (expected_name, expected_value)) in pairs.iter().zip(expected) {
+        assert!(name == expected_name && value == expected_value, "{name}={value}");
+    }
+
+    assert!(parse_urlencoded("").unwrap().is_empty());
+    assert!(parse_urlencoded("a=%zz").unwrap_err() == ResponseCode::get_400());
+    assert!(parse_urlencoded("a=%FF").unwrap_err() == ResponseCode::get_400());
+}
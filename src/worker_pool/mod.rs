@@ -1,14 +1,767 @@
-use std::fmt::Display;
-use std::sync::mpsc::Receiver;
-use std::sync::{mpsc, Arc, Mutex};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Display};
+use std::io;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::log;
 
 /// A callable to be send to a thread for execution.
 type Job = Box<dyn FnOnce() + Send>;
 
-/// A pointer to a reciever object of [Job]s that is shared between workers in a
-/// mutually exclusive fashion [Mutex].
-type MultiReceiver = Arc<Mutex<Receiver<Job>>>;
+/// Called with the id of a [Worker] on its thread, see [PoolBuilder::on_thread_start].
+type Hook = Arc<dyn Fn(usize) + Send + Sync>;
+
+/// Most jobs a worker moves from the global queue to its own deque at once.
+const MAX_BATCH: usize = 32;
+
+/// Every this many jobs, a worker takes the oldest job of the lowest
+/// [Priority] waiting instead of the highest, so none starves.
+const FAIRNESS: usize = 16;
+
+/// Order in which workers take jobs, see [Pool::execute_with_priority].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High
+}
+
+/// Jobs of the global queue, first in first out per [Priority].
+#[derive(Default)]
+struct Jobs {
+    levels: [VecDeque<Job>; 3]
+}
+
+impl Jobs {
+    fn len(&self) -> usize {
+        self.levels.iter().map(VecDeque::len).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.levels.iter().all(VecDeque::is_empty)
+    }
+
+    fn get(&self, priority: Priority) -> &VecDeque<Job> {
+        &self.levels[priority as usize]
+    }
+
+    fn get_mut(&mut self, priority: Priority) -> &mut VecDeque<Job> {
+        &mut self.levels[priority as usize]
+    }
+
+    /// Takes the oldest job of the highest priority, or of the lowest if [lowest_first].
+    fn pop(&mut self, lowest_first: bool) -> Option<(Priority, Job)> {
+        let mut order = [Priority::High, Priority::Normal, Priority::Low];
+        if lowest_first {
+            order.reverse();
+        }
+        order.into_iter().find_map(|priority| self.get_mut(priority).pop_front().map(|job| (priority, job)))
+    }
+
+    fn drain(&mut self) -> impl Iterator<Item = Job> + '_ {
+        self.levels.iter_mut().flat_map(|level| level.drain(..))
+    }
+}
+
+/// Global queue of a [Queue], guarded by its [Mutex].
+/// Jobs sent from outside the pool wait here until a worker takes a batch.
+struct QueueState {
+    jobs: Jobs,
+    closed: bool,
+    /// Live workers, including ones about to be spawned.
+    workers: usize,
+    /// Workers waiting for a job.
+    idle: usize,
+    /// Idle workers signalled by [Queue::push] that have not woken up yet,
+    /// so a burst of jobs does not signal the same worker over and over.
+    woken: usize
+}
+
+/// Deque of one [Worker], only holding jobs of [Priority::Normal].
+/// Its owner takes jobs from it before the global queue unless a job of
+/// [Priority::High] is waiting there, idle workers steal half of it
+/// when the global queue has nothing above [Priority::Low].
+#[derive(Default)]
+struct Local {
+    jobs: Mutex<VecDeque<Job>>,
+    /// Jobs its owner took, for [FAIRNESS].
+    taken: AtomicUsize
+}
+
+impl Local {
+    /// Private helper to lock the deque, which is consistent even if poisoned.
+    fn lock(&self) -> MutexGuard<'_, VecDeque<Job>> {
+        self.jobs.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The worker running on this thread.
+struct Current {
+    /// Address of its [Queue].
+    queue: usize,
+    id: usize,
+    local: Arc<Local>
+}
+
+thread_local! {
+    /// Set on worker threads, so jobs sent from a job skip the global queue
+    /// and [Pool::scope] can run jobs while it waits.
+    static CURRENT: RefCell<Option<Current>> = const { RefCell::new(None) };
+}
+
+/// How many workers a [Pool] runs.
+#[derive(Debug, Clone, Copy)]
+struct Sizing {
+    min: usize,
+    max: usize,
+    /// Workers above [min] exit after waiting this long for a job.
+    /// None keeps all workers until drop.
+    idle_timeout: Option<Duration>
+}
+
+/// [Job]s shared between the [Pool] and its [Worker]s: a global queue,
+/// optionally bounded, in which case producers wait for space,
+/// and a deque per worker that the others steal from.
+///
+/// Locks are taken in the order global queue, [locals](Queue::locals), [Local],
+/// and never more than one [Local] at a time.
+struct Queue {
+    state: Mutex<QueueState>,
+    /// Signalled when a job is pushed or the queue is closed.
+    available: Condvar,
+    /// Signalled when a job is taken, for producers waiting for space.
+    space: Condvar,
+    /// Signalled when a worker retires, for [Pool::shutdown].
+    stopped: Condvar,
+    /// Deques of the live workers.
+    locals: RwLock<Vec<Arc<Local>>>,
+    /// Copy of [QueueState::idle] for [push_local], which does not take the lock.
+    sleeping: AtomicUsize,
+    /// Jobs in the deques of all workers, so idle workers only look
+    /// through the deques when there is something to steal.
+    in_locals: AtomicUsize,
+    /// Jobs of [Priority::High] in the global queue, so workers only take
+    /// the lock before their own deque when there is one.
+    urgent: AtomicUsize,
+    capacity: Option<usize>,
+    sizing: Sizing,
+    stats: Arc<PoolStats>
+}
+
+/// A pointer to the [Queue] of [Job]s that is shared between workers.
+type MultiReceiver = Arc<Queue>;
+
+impl Queue {
+    fn new(capacity: Option<usize>, sizing: Sizing, stats: Arc<PoolStats>) -> Queue {
+        stats.workers.store(sizing.min, Ordering::Relaxed);
+        Queue {
+            state: Mutex::new(
+                QueueState {
+                    jobs: Jobs::default(),
+                    closed: false,
+                    workers: sizing.min,
+                    idle: 0,
+                    woken: 0
+                }
+            ),
+            available: Condvar::new(),
+            space: Condvar::new(),
+            stopped: Condvar::new(),
+            locals: RwLock::new(Vec::with_capacity(sizing.max)),
+            sleeping: AtomicUsize::new(0),
+            in_locals: AtomicUsize::new(0),
+            urgent: AtomicUsize::new(0),
+            capacity,
+            sizing,
+            stats
+        }
+    }
+
+    /// Private helper to lock the state. Jobs never run under the lock,
+    /// so a poisoned lock still holds a consistent queue.
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Whether all jobs, queued globally or in a worker's deque, fill the capacity.
+    fn is_full(&self) -> bool {
+        self.capacity.is_some_and(|capacity| self.stats.get_queued() >= capacity)
+    }
+
+    /// Waits for space until the deadline (forever if None) and returns
+    /// the locked state to [push] into, or None if there was no space in time.
+    fn wait_for_space(&self, deadline: Option<Instant>) -> Option<MutexGuard<'_, QueueState>> {
+        let mut state = self.lock();
+        while self.is_full() {
+            state = match deadline {
+                None => self.space.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None
+                    }
+                    self.space.wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()).0
+                }
+            };
+        }
+        Some(state)
+    }
+
+    /// Pushes the job into the state locked by [wait_for_space].
+    /// Returns true if no worker is left to take the job and one more may be
+    /// started, in which case the caller must spawn it (it is already counted).
+    #[must_use]
+    fn push(&self, mut state: MutexGuard<'_, QueueState>, priority: Priority, job: Job) -> bool {
+        state.jobs.get_mut(priority).push_back(job);
+        if priority == Priority::High {
+            self.urgent.fetch_add(1, Ordering::Relaxed);
+        }
+        self.stats.queued.fetch_add(1, Ordering::Relaxed);
+
+        let spawn = state.jobs.len() > state.idle && state.workers < self.sizing.max;
+        if spawn {
+            state.workers += 1;
+            self.stats.workers.store(state.workers, Ordering::Relaxed);
+        }
+        // Busy workers look for jobs before waiting, only idle ones need a signal,
+        // and no more of them than there are jobs waiting
+        let wake = state.idle > state.woken && state.jobs.len() > state.woken;
+        if wake {
+            state.woken += 1;
+        }
+        drop(state);
+        if wake {
+            self.available.notify_one();
+        }
+        spawn
+    }
+
+    /// Returns the id and deque of the worker running on this thread,
+    /// if it is a worker of this queue.
+    fn current(&self) -> Option<(usize, Arc<Local>)> {
+        let address = self as *const Queue as usize;
+        CURRENT.with(|current| match &*current.borrow() {
+            Some(current) if current.queue == address => Some((current.id, current.local.clone())),
+            _ => None
+        })
+    }
+
+    /// Pushes a job sent from a job into the deque of its worker, without the
+    /// global lock. It does not wait for space, a worker waiting for its
+    /// own pool could wait forever.
+    fn push_local(&self, local: &Local, job: Job) {
+        let was_empty = {
+            let mut jobs = local.lock();
+            jobs.push_back(job);
+            jobs.len() == 1
+        };
+        self.in_locals.fetch_add(1, Ordering::SeqCst);
+        self.stats.queued.fetch_add(1, Ordering::Relaxed);
+
+        // Pairs with the fence in [pop]: either a worker going to sleep
+        // finds this job, or it is counted as sleeping here and woken up.
+        // Jobs pushed after it are left for that worker to steal, see [steal].
+        fence(Ordering::SeqCst);
+        if was_empty {
+            self.wake_sleeper();
+        }
+    }
+
+    /// Private helper to wake one idle worker, if any, without the lock held.
+    fn wake_sleeper(&self) {
+        if self.sleeping.load(Ordering::SeqCst) > 0 {
+            let _state = self.lock();
+            self.available.notify_one();
+        }
+    }
+
+    /// Adds the deque of a new worker, for the others to steal from.
+    fn register(&self, local: Arc<Local>) {
+        self.locals.write().unwrap_or_else(|poisoned| poisoned.into_inner()).push(local);
+    }
+
+    /// Takes the next job for the worker owning [local], waiting for one.
+    /// Returns None once the queue is closed and all jobs are taken, or once
+    /// the worker was idle for too long and is not needed, after which it must exit.
+    fn pop(&self, local: &Local) -> Option<Job> {
+        let mut timed_out = false;
+        loop {
+            if let Some(job) = self.find(local) {
+                return Some(self.taken(job))
+            }
+
+            let mut state = self.lock();
+            if !state.jobs.is_empty() {
+                continue
+            }
+
+            state.idle += 1;
+            self.sleeping.store(state.idle, Ordering::SeqCst);
+            // Pairs with the fence in [push_local]
+            fence(Ordering::SeqCst);
+            let stolen = self.steal(local);
+
+            if stolen.is_none() {
+                if state.closed || (timed_out && state.workers > self.sizing.min) {
+                    state.idle -= 1;
+                    self.sleeping.store(state.idle, Ordering::SeqCst);
+                    return self.retire(state, local)
+                }
+
+                timed_out = match self.sizing.idle_timeout {
+                    None => {
+                        state = self.available.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+                        false
+                    }
+                    Some(idle_timeout) => {
+                        let (guard, result) = self.available.wait_timeout(state, idle_timeout)
+                            .unwrap_or_else(|poisoned| poisoned.into_inner());
+                        state = guard;
+                        result.timed_out()
+                    }
+                };
+                // Woken by push or not, this worker looks for jobs again
+                state.woken = state.woken.saturating_sub(1);
+            }
+
+            state.idle -= 1;
+            self.sleeping.store(state.idle, Ordering::SeqCst);
+            if let Some(job) = stolen {
+                drop(state);
+                if !local.lock().is_empty() {
+                    self.wake_sleeper();
+                }
+                return Some(self.taken(job))
+            }
+        }
+    }
+
+    /// Private helper to find a job without waiting: a job of [Priority::High]
+    /// from the global queue, then from the worker's own deque, then a batch
+    /// from the global queue, then stolen from another worker, and only then
+    /// a job of [Priority::Low] from the global queue.
+    /// Every [FAIRNESS]th job is the oldest of the lowest priority instead.
+    fn find(&self, local: &Local) -> Option<Job> {
+        let fair_turn = local.taken.load(Ordering::Relaxed) % FAIRNESS == FAIRNESS - 1;
+        let job = self.find_job(local, fair_turn);
+        if job.is_some() {
+            local.taken.fetch_add(1, Ordering::Relaxed);
+        }
+        job
+    }
+
+    /// Private helper for [find].
+    fn find_job(&self, local: &Local, fair_turn: bool) -> Option<Job> {
+        if (fair_turn || self.urgent.load(Ordering::Relaxed) > 0)
+            && let Some((_, job)) = self.take_global(&mut self.lock(), fair_turn) {
+            return Some(job)
+        }
+
+        if let Some(job) = local.lock().pop_front() {
+            self.in_locals.fetch_sub(1, Ordering::SeqCst);
+            return Some(job)
+        }
+
+        let mut state = self.lock();
+        // Jobs of Priority::Low wait for the ones other workers hold
+        let only_low = state.jobs.len() == state.jobs.get(Priority::Low).len();
+        if !only_low && let Some((priority, job)) = self.take_global(&mut state, false) {
+            if priority != Priority::Normal {
+                return Some(job)
+            }
+            // Leave enough for the other workers
+            let normal = state.jobs.get(Priority::Normal).len();
+            let batch = (normal / state.workers.max(1)).min(MAX_BATCH - 1);
+            let rest: Vec<Job> = state.jobs.get_mut(Priority::Normal).drain(..batch).collect();
+            self.in_locals.fetch_add(rest.len(), Ordering::SeqCst);
+            drop(state);
+            local.lock().extend(rest);
+            return Some(job)
+        }
+        drop(state);
+
+        if let Some(job) = self.steal(local) {
+            if !local.lock().is_empty() {
+                self.wake_sleeper();
+            }
+            return Some(job)
+        }
+
+        self.take_global(&mut self.lock(), false).map(|(_, job)| job)
+    }
+
+    /// Private helper to take a job from the global queue, see [Jobs::pop].
+    fn take_global(&self, state: &mut QueueState, lowest_first: bool) -> Option<(Priority, Job)> {
+        let (priority, job) = state.jobs.pop(lowest_first)?;
+        if priority == Priority::High {
+            self.urgent.fetch_sub(1, Ordering::Relaxed);
+        }
+        Some((priority, job))
+    }
+
+    /// Private helper to take half the jobs of the first other worker that has any.
+    /// Returns the first, the others go to the deque of [local], and the
+    /// caller should [wake_sleeper] to steal from there in turn.
+    fn steal(&self, local: &Local) -> Option<Job> {
+        if self.in_locals.load(Ordering::SeqCst) == 0 {
+            return None
+        }
+
+        let locals = self.locals.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for victim in locals.iter().filter(|victim| !std::ptr::eq(victim.as_ref(), local)) {
+            let mut stolen = {
+                let mut jobs = victim.lock();
+                let half = jobs.len().div_ceil(2);
+                jobs.drain(..half).collect::<VecDeque<Job>>()
+            };
+            if let Some(job) = stolen.pop_front() {
+                self.in_locals.fetch_sub(1, Ordering::SeqCst);
+                local.lock().extend(stolen);
+                return Some(job)
+            }
+        }
+        None
+    }
+
+    /// Private helper to count a job as taken off the queue, without the lock held.
+    fn taken(&self, job: Job) -> Job {
+        self.stats.queued.fetch_sub(1, Ordering::Relaxed);
+        self.stats.busy.fetch_add(1, Ordering::Relaxed);
+        if self.capacity.is_some() {
+            // Under the lock, so a producer can't miss it between its check and its wait
+            let _state = self.lock();
+            self.space.notify_one();
+        }
+        job
+    }
+
+    /// Private helper to count out a worker that is about to exit.
+    fn retire(&self, mut state: MutexGuard<'_, QueueState>, local: &Local) -> Option<Job> {
+        state.workers -= 1;
+        self.stats.workers.store(state.workers, Ordering::Relaxed);
+        self.locals.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .retain(|other| !std::ptr::eq(other.as_ref(), local));
+        drop(state);
+        self.stopped.notify_all();
+        None
+    }
+
+    /// Removes all queued jobs, for the caller to drop outside the locks.
+    fn discard(&self) -> Vec<Job> {
+        let mut state = self.lock();
+        let mut jobs: Vec<Job> = state.jobs.drain().collect();
+        self.urgent.store(0, Ordering::Relaxed);
+        drop(state);
+        for local in self.locals.read().unwrap_or_else(|poisoned| poisoned.into_inner()).iter() {
+            let mut local_jobs = local.lock();
+            self.in_locals.fetch_sub(local_jobs.len(), Ordering::SeqCst);
+            jobs.extend(local_jobs.drain(..));
+        }
+        self.stats.queued.fetch_sub(jobs.len(), Ordering::Relaxed);
+        let _state = self.lock();
+        self.space.notify_all();
+        jobs
+    }
+
+    /// Waits until all workers retired, or with [leave_busy] until only
+    /// workers running a job are left, or until the deadline (forever if None).
+    /// Returns the number of workers left. The queue must be closed.
+    fn wait_stopped(&self, deadline: Option<Instant>, leave_busy: bool) -> usize {
+        let mut state = self.lock();
+        loop {
+            let target = if leave_busy { self.stats.get_busy() } else { 0 };
+            if state.workers <= target {
+                return state.workers
+            }
+            state = match deadline {
+                None => self.stopped.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner()),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return state.workers
+                    }
+                    self.stopped.wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()).0
+                }
+            };
+        }
+    }
+
+    /// Lets workers finish the remaining jobs and stop.
+    fn close(&self) {
+        self.lock().closed = true;
+        self.available.notify_all();
+    }
+}
+
+/// Why [Pool::try_execute] or [Pool::execute_timeout] did not accept a callable.
+/// The callable is handed back, e.g. to run it elsewhere or to answer a client.
+pub enum ExecuteError<F> {
+    /// The queue was full.
+    Full(F),
+    /// The queue stayed full until the timeout.
+    TimedOut(F)
+}
+
+impl<F> ExecuteError<F> {
+    /// Returns the callable that was not executed.
+    pub fn into_inner(self) -> F {
+        match self {
+            ExecuteError::Full(callable) | ExecuteError::TimedOut(callable) => callable
+        }
+    }
+}
+
+impl<F> Debug for ExecuteError<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecuteError::Full(_) => write!(f, "Full(..)"),
+            ExecuteError::TimedOut(_) => write!(f, "TimedOut(..)")
+        }
+    }
+}
+
+impl<F> Display for ExecuteError<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecuteError::Full(_) => write!(f, "job queue is full"),
+            ExecuteError::TimedOut(_) => write!(f, "job queue stayed full until the timeout")
+        }
+    }
+}
+
+impl<F> std::error::Error for ExecuteError<F> {}
+
+/// Why a [JobHandle] has no value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobError {
+    /// The job panicked with this message.
+    Panicked(String),
+    /// The job was dropped without running, or its value was already taken.
+    Lost
+}
+
+impl Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobError::Panicked(message) => write!(f, "job panicked: {message}"),
+            JobError::Lost => write!(f, "job result is not available")
+        }
+    }
+}
+
+impl std::error::Error for JobError {}
+
+/// Outcome of [Pool::shutdown] and [Pool::shutdown_now].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    completed: usize,
+    discarded: usize,
+    unstopped: usize
+}
+
+impl ShutdownReport {
+    /// Jobs that ran to completion since the pool was created, not counting panicked ones.
+    pub const fn get_completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Queued jobs that were dropped without running.
+    pub const fn get_discarded(&self) -> usize {
+        self.discarded
+    }
+
+    /// Workers that were still running a job when the pool stopped waiting.
+    /// They are detached and exit once their job returns.
+    pub const fn get_unstopped(&self) -> usize {
+        self.unstopped
+    }
+}
+
+/// Value of a job sent with [Pool::submit].
+/// Once one of the join methods returned a value or error, the handle is spent
+/// and further calls return [JobError::Lost].
+#[derive(Debug)]
+pub struct JobHandle<T> {
+    /// None once spent.
+    rx: Option<mpsc::Receiver<Result<T, JobError>>>
+}
+
+impl<T> JobHandle<T> {
+    /// Waits for the job to finish.
+    pub fn join(mut self) -> Result<T, JobError> {
+        match self.rx.take() {
+            Some(rx) => rx.recv().unwrap_or(Result::Err(JobError::Lost)),
+            None => Result::Err(JobError::Lost)
+        }
+    }
+
+    /// Returns the result if the job has finished, None otherwise.
+    pub fn try_join(&mut self) -> Option<Result<T, JobError>> {
+        let Some(rx) = self.rx.as_ref() else {
+            return Some(Result::Err(JobError::Lost))
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Result::Err(JobError::Lost)
+        };
+        self.rx = None;
+        Some(result)
+    }
+
+    /// Waits up to [timeout] for the job to finish, None if it did not.
+    pub fn join_timeout(&mut self, timeout: Duration) -> Option<Result<T, JobError>> {
+        let Some(rx) = self.rx.as_ref() else {
+            return Some(Result::Err(JobError::Lost))
+        };
+        let result = match rx.recv_timeout(timeout) {
+            Ok(result) => result,
+            Err(mpsc::RecvTimeoutError::Timeout) => return None,
+            Err(mpsc::RecvTimeoutError::Disconnected) => Result::Err(JobError::Lost)
+        };
+        self.rx = None;
+        Some(result)
+    }
+}
+
+/// Jobs of a [Scope] that have not finished, and the first panic among them.
+#[derive(Default)]
+struct ScopeState {
+    pending: Mutex<(usize, Option<Box<dyn Any + Send>>)>,
+    /// Signalled when a job of the scope finishes.
+    done: Condvar
+}
+
+impl ScopeState {
+    /// Private helper to lock the state, which is consistent even if poisoned.
+    fn lock(&self) -> MutexGuard<'_, (usize, Option<Box<dyn Any + Send>>)> {
+        self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Sends jobs that borrow from the stack of [Pool::scope]'s caller,
+/// same as [std::thread::Scope] but run by the pool's workers.
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'scope Pool,
+    state: Arc<ScopeState>,
+    /// Invariant lifetimes, as in [std::thread::Scope].
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>
+}
+
+impl<'scope> Scope<'scope, '_> {
+    /// Same as [Pool::execute], but callable may borrow anything that outlives the scope.
+    /// If it panics, the panic is passed on by [Pool::scope] once all jobs finished.
+    pub fn execute<F>(&'scope self, callable: F)
+    where
+        F: FnOnce() + Send + 'scope
+    {
+        self.state.lock().0 += 1;
+
+        let state = self.state.clone();
+        let stats = self.pool.stats.clone();
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            // Callable and its borrows are dropped before the scope is told it is done
+            let result = panic::catch_unwind(AssertUnwindSafe(callable));
+
+            let mut pending = state.lock();
+            if let Err(payload) = result {
+                stats.panicked.fetch_add(1, Ordering::Relaxed);
+                pending.1.get_or_insert(payload);
+            }
+            pending.0 -= 1;
+            drop(pending);
+            state.done.notify_all();
+        });
+        // SAFETY: [Pool::scope] does not return before every job of the scope
+        // finished, so nothing the job borrows for 'scope is gone while it runs.
+        let job: Job = unsafe {
+            std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, Job>(job)
+        };
+        self.pool.send(Priority::Normal, job);
+    }
+}
+
+/// Live counters of a [Pool], shared with its [Worker]s.
+/// Read by monitoring such as metrics and readiness checks.
+#[derive(Debug, Default)]
+pub struct PoolStats {
+    workers: AtomicUsize,
+    queued: AtomicUsize,
+    busy: AtomicUsize,
+    completed: AtomicUsize,
+    panicked: AtomicUsize
+}
+
+impl PoolStats {
+    pub fn get_workers(&self) -> usize {
+        self.workers.load(Ordering::Relaxed)
+    }
+
+    /// Jobs sent to the pool that no worker has picked up yet.
+    pub fn get_queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Workers that are running a job.
+    pub fn get_busy(&self) -> usize {
+        self.busy.load(Ordering::Relaxed)
+    }
+
+    /// Jobs that returned since the pool was created.
+    pub fn get_completed(&self) -> usize {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    /// Jobs that panicked since the pool was created.
+    pub fn get_panicked(&self) -> usize {
+        self.panicked.load(Ordering::Relaxed)
+    }
+}
+
+/// Returns the message of a panic payload, as passed to [panic!].
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic payload"
+    }
+}
+
+/// How [Worker] threads are spawned, set with [PoolBuilder].
+#[derive(Clone)]
+struct Threads {
+    name_prefix: String,
+    stack_size: Option<usize>,
+    on_start: Option<Hook>,
+    on_stop: Option<Hook>
+}
+
+impl Threads {
+    /// Private helper to run a hook, a panic in it is logged and does not stop the worker.
+    fn run_hook(hook: &Option<Hook>, id: usize, name: &str) {
+        if let Some(hook) = hook
+            && let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| hook(id))) {
+            log::error(
+                module_path!(),
+                "worker hook panicked",
+                &[("worker", &id), ("hook", &name), ("panic", &panic_message(payload.as_ref()))]
+            );
+        }
+    }
+}
 
 /// Workers take ownership of the thread which runs [Job]s.
 struct Worker {
@@ -18,31 +771,88 @@ struct Worker {
 
 impl Worker {
     /// Creates a new worker with thread and id.
+    /// A panicking job is caught and logged, the worker moves on to the next one.
     /// 
     /// # Parameters
     /// in_id - is a numeric id assigned by [Pool]. Only used for human comprehension.
-    /// in_rx - is a [MultiReceiver].
-    fn new(in_id: usize, in_rx: MultiReceiver) -> Worker{
-        let thread_h = thread::spawn(
-            move || loop {
-                let job = in_rx.lock().unwrap().recv();
-
-                match job {
-                    Ok(callable) => {
-                        println!("worker {in_id} recieved a job.");
-                        callable();
-                    },
-                    Err(_) => {
-                        eprintln!("worker {in_id} could not recieve a job. Shutting down.");
-                        break;
+    /// in_rx - is a [MultiReceiver], which the worker's own deque is registered with.
+    /// in_stats - counters of the [Pool] to update.
+    /// in_threads - name, stack size and hooks of the thread.
+    /// 
+    /// The worker must already be counted by the queue. If its thread can't be
+    /// spawned, it is counted out again and the error is returned.
+    fn new(in_id: usize, in_rx: MultiReceiver, in_stats: Arc<PoolStats>, in_threads: &Threads) -> Result<Worker, io::Error> {
+        let local = Arc::new(Local::default());
+        in_rx.register(local.clone());
+
+        let mut builder = thread::Builder::new().name(format!("{}-{in_id}", in_threads.name_prefix));
+        if let Some(stack_size) = in_threads.stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+        let threads = in_threads.clone();
+        // Kept to count the worker out if its thread can't be spawned
+        let (queue, own_local) = (in_rx.clone(), local.clone());
+
+        let spawned = builder.spawn(
+            move || {
+                Threads::run_hook(&threads.on_start, in_id, "start");
+                CURRENT.with(|current| {
+                    *current.borrow_mut() = Some(
+                        Current {
+                            queue: Arc::as_ptr(&in_rx) as usize,
+                            id: in_id,
+                            local: local.clone()
+                        }
+                    );
+                });
+
+                loop {
+                    match in_rx.pop(&local) {
+                        Some(callable) => {
+                            Worker::run(in_id, callable, &in_stats);
+                            // Busy is counted by the queue when the job is taken
+                            in_stats.busy.fetch_sub(1, Ordering::Relaxed);
+                        },
+                        None => {
+                            // Queue is closed by the pool on shutdown or drop, or the worker idled out
+                            log::debug(module_path!(), "worker shutting down", &[("worker", &in_id)]);
+                            break;
+                        }
                     }
                 }
+                Threads::run_hook(&threads.on_stop, in_id, "stop");
             }
         );
 
-        Worker {
-            id: in_id,
-            thread_h: thread_h
+        match spawned {
+            Ok(thread_h) => Result::Ok(
+                Worker {
+                    id: in_id,
+                    thread_h
+                }
+            ),
+            Err(err) => {
+                queue.retire(queue.lock(), &own_local);
+                Result::Err(err)
+            }
+        }
+    }
+
+    /// Runs a job, catching and counting its panic.
+    fn run(id: usize, callable: Job, stats: &PoolStats) {
+        log::debug(module_path!(), "worker received a job", &[("worker", &id)]);
+        match panic::catch_unwind(AssertUnwindSafe(callable)) {
+            Ok(()) => {
+                stats.completed.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(payload) => {
+                stats.panicked.fetch_add(1, Ordering::Relaxed);
+                log::error(
+                    module_path!(),
+                    "job panicked",
+                    &[("worker", &id), ("panic", &panic_message(payload.as_ref()))]
+                );
+            }
         }
     }
 }
@@ -53,11 +863,180 @@ impl Display for Worker {
     }
 }
 
+/// Why [PoolBuilder::build] could not create a [Pool].
+#[derive(Debug)]
+pub enum PoolError {
+    /// The number of workers, or the maximum of an elastic pool, is outside of the range [1, 256).
+    WorkerCount(usize),
+    /// The minimum of an elastic pool is above its maximum.
+    MinAboveMax { min: usize, max: usize },
+    /// A bounded queue must hold at least one job.
+    ZeroCapacity,
+    /// The thread of a worker could not be spawned.
+    Spawn(io::Error)
+}
+
+impl Display for PoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolError::WorkerCount(n_workers) => write!(f, "{n_workers} workers is outside of the range [1, 256)"),
+            PoolError::MinAboveMax { min, max } => write!(f, "min of {min} workers is above max of {max}"),
+            PoolError::ZeroCapacity => write!(f, "capacity must be at least 1"),
+            PoolError::Spawn(err) => write!(f, "could not spawn a worker thread: {err}")
+        }
+    }
+}
+
+impl std::error::Error for PoolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PoolError::Spawn(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+/// Configures a [Pool] and its threads, created with [Pool::builder].
+/// By default, the pool runs one worker per available CPU with an unbounded queue.
+/// 
+/// # Example
+/// ```
+/// use std::cell::Cell;
+/// 
+/// use rns::worker_pool::Pool;
+/// 
+/// thread_local! {
+///     static WORKER_ID: Cell<Option<usize>> = const { Cell::new(None) };
+/// }
+/// 
+/// let pool = Pool::builder()
+///     .workers(4)
+///     .thread_name("db")
+///     .stack_size(512 * 1024)
+///     .on_thread_start(|id| WORKER_ID.set(Some(id)))
+///     .build()
+///     .unwrap();
+/// 
+/// let handle = pool.submit(|| (std::thread::current().name().map(String::from), WORKER_ID.get()));
+/// let (name, id) = handle.join().unwrap();
+/// assert!(name.unwrap().starts_with("db-"));
+/// assert!(id.is_some());
+/// 
+/// assert!(Pool::builder().workers(0).build().is_err());
+/// ```
+pub struct PoolBuilder {
+    sizing: Sizing,
+    capacity: Option<usize>,
+    threads: Threads
+}
+
+impl Default for PoolBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolBuilder {
+    pub fn new() -> PoolBuilder {
+        let n_workers = thread::available_parallelism().map_or(1, |n| n.get().min(255));
+        PoolBuilder {
+            sizing: Sizing { min: n_workers, max: n_workers, idle_timeout: None },
+            capacity: None,
+            threads: Threads {
+                name_prefix: "rns-worker".to_string(),
+                stack_size: None,
+                on_start: None,
+                on_stop: None
+            }
+        }
+    }
+
+    /// A fixed number of workers, which must be in range [1, 256), see [Pool::new].
+    pub fn workers(mut self, n_workers: usize) -> PoolBuilder {
+        self.sizing = Sizing { min: n_workers, max: n_workers, idle_timeout: None };
+        self
+    }
+
+    /// Between [min] and [max] workers, see [Pool::elastic].
+    pub fn elastic(mut self, min: usize, max: usize, idle_timeout: Duration) -> PoolBuilder {
+        self.sizing = Sizing { min, max, idle_timeout: Some(idle_timeout) };
+        self
+    }
+
+    /// Bounds the queue, see [Pool::bounded].
+    pub fn capacity(mut self, capacity: usize) -> PoolBuilder {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Threads are named "<prefix>-<id>", e.g. in panic messages and in `top -H`.
+    pub fn thread_name(mut self, prefix: &str) -> PoolBuilder {
+        self.threads.name_prefix = prefix.to_string();
+        self
+    }
+
+    /// Stack size of the threads in bytes, the default of [std::thread] otherwise.
+    pub fn stack_size(mut self, stack_size: usize) -> PoolBuilder {
+        self.threads.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Runs on each worker thread before it takes any job, with the id of the worker,
+    /// e.g. to set up thread local state. A panic in it is logged and ignored.
+    pub fn on_thread_start<F>(mut self, hook: F) -> PoolBuilder
+    where
+        F: Fn(usize) + Send + Sync + 'static
+    {
+        self.threads.on_start = Some(Arc::new(hook));
+        self
+    }
+
+    /// Runs on each worker thread once it took its last job, with the id of the worker.
+    /// Workers that did not stop in time on [Pool::shutdown] run it when they do.
+    pub fn on_thread_stop<F>(mut self, hook: F) -> PoolBuilder
+    where
+        F: Fn(usize) + Send + Sync + 'static
+    {
+        self.threads.on_stop = Some(Arc::new(hook));
+        self
+    }
+
+    /// Creates the pool and starts its initial workers.
+    pub fn build(self) -> Result<Pool, PoolError> {
+        let Sizing { min, max, .. } = self.sizing;
+        if max == 0 || max >= 256 {
+            return Result::Err(PoolError::WorkerCount(max))
+        }
+        if min > max {
+            return Result::Err(PoolError::MinAboveMax { min, max })
+        }
+        if self.capacity == Some(0) {
+            return Result::Err(PoolError::ZeroCapacity)
+        }
+
+        Pool::create(self.sizing, self.capacity, self.threads)
+    }
+}
+
 /// Pool owns [Worker] instances and sends them [Job]s for execution.
-/// Pool manages graceful shutdown of workers as seen in drop implementation.
+/// Pool manages graceful shutdown of workers as seen in drop implementation,
+/// which waits for all queued jobs. [shutdown](Pool::shutdown) and
+/// [shutdown_now](Pool::shutdown_now) bound the wait and report the outcome.
+/// Jobs wait in a queue until a worker is free, the queue is unbounded
+/// unless the pool is created with [bounded](Pool::bounded).
+/// Workers take jobs from the queue in batches into their own deques and
+/// steal from each other when idle, so they rarely contend for one lock.
+/// Jobs sent from a job go straight to the deque of the worker running it.
+/// An [elastic](Pool::elastic) pool starts and stops workers with the load.
+/// Jobs sent with [execute_with_priority](Pool::execute_with_priority) of a
+/// higher [Priority] are taken first.
+/// Threads are named "rns-worker-<id>" unless configured otherwise with [builder](Pool::builder).
 pub struct Pool {    
-    tx: Option<mpsc::Sender<Job>>,
-    workers: Vec<Worker>
+    queue: Arc<Queue>,
+    workers: Mutex<Vec<Worker>>,
+    next_id: AtomicUsize,
+    stats: Arc<PoolStats>,
+    threads: Threads
 }
 
 impl Pool {
@@ -80,28 +1059,119 @@ impl Pool {
     /// let pool = Pool::new(4);
     /// ```
     pub fn new(n_workers: usize) -> Pool {
-        assert!(
-            n_workers > 0 && n_workers < 256,
-            "n_workers must be in range [1, 256)"
-        );
+        Pool::builder().workers(n_workers).build().unwrap_or_else(|err| panic!("{err}"))
+    }
 
-        let (tx , rx) = mpsc::channel();
-        let rx: MultiReceiver = Arc::new(Mutex::new(rx));
-        let mut workers = Vec::with_capacity(n_workers);
+    /// Creates a new pool that starts with [min] workers and starts more, up to [max],
+    /// when jobs are queued and no worker is idle. Workers above [min] exit once
+    /// they have waited [idle_timeout] for a job.
+    /// 
+    /// # Panics
+    /// Will panic if max is outside of the range [1, 256) or min is above max.
+    /// 
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// 
+    /// use rns::worker_pool::Pool;
+    /// 
+    /// // One worker when quiet, up to 16 in a burst
+    /// let pool = Pool::elastic(1, 16, Duration::from_secs(30));
+    /// ```
+    pub fn elastic(min: usize, max: usize, idle_timeout: Duration) -> Pool {
+        Pool::builder().elastic(min, max, idle_timeout).build().unwrap_or_else(|err| panic!("{err}"))
+    }
 
-        for i in 0..n_workers {
-            workers.push(
-                Worker::new(i, rx.clone())
-            );
+    /// Creates a new pool whose queue holds at most [capacity] jobs.
+    /// When the queue is full, [execute] waits for space, [try_execute]
+    /// fails right away and [execute_timeout] waits up to a timeout.
+    /// Jobs that [execute] sends from a job of the pool are not bounded, since
+    /// a worker waiting for its own pool could wait forever.
+    /// 
+    /// # Panics
+    /// Same as [new], and will panic if capacity is 0.
+    /// 
+    /// # Example
+    /// ```
+    /// use rns::worker_pool::Pool;
+    /// 
+    /// let pool = Pool::bounded(4, 64);
+    /// 
+    /// if let Err(err) = pool.try_execute(|| {}) {
+    ///     println!("overloaded: {err}");
+    /// }
+    /// ```
+    pub fn bounded(n_workers: usize, capacity: usize) -> Pool {
+        Pool::builder().workers(n_workers).capacity(capacity).build().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Returns a [PoolBuilder] to configure the threads of a pool,
+    /// which reports invalid settings as errors instead of panicking.
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder::new()
+    }
+
+    /// Private helper to create a pool and its initial workers.
+    fn create(sizing: Sizing, capacity: Option<usize>, threads: Threads) -> Result<Pool, PoolError> {
+        let stats = Arc::new(PoolStats::default());
+        let queue: MultiReceiver = Arc::new(Queue::new(capacity, sizing, stats.clone()));
+
+        let mut pool = Pool {
+            queue,
+            workers: Mutex::new(Vec::with_capacity(sizing.max)),
+            next_id: AtomicUsize::new(sizing.min),
+            stats,
+            threads
+        };
+        for i in 0..sizing.min {
+            // On error, dropping the pool stops the workers started so far
+            let worker = Worker::new(i, pool.queue.clone(), pool.stats.clone(), &pool.threads)
+                .map_err(PoolError::Spawn)?;
+            pool.workers.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner()).push(worker);
         }
+        Result::Ok(pool)
+    }
+
+    /// Private helper to start a worker that [Queue::push] asked for.
+    /// If its thread can't be spawned, the job waits for the next worker.
+    fn spawn_worker(&self) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let worker = match Worker::new(id, self.queue.clone(), self.stats.clone(), &self.threads) {
+            Ok(worker) => worker,
+            Err(err) => {
+                log::error(module_path!(), "could not start a worker", &[("worker", &id), ("error", &err)]);
+                return
+            }
+        };
+        log::debug(module_path!(), "worker started", &[("worker", &id)]);
 
-        Pool {
-            tx: Some(tx),
-            workers: workers
+        let mut workers = self.workers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Workers that idled out are gone, no need to keep their handles
+        workers.retain(|worker| !worker.thread_h.is_finished());
+        workers.push(worker);
+    }
+
+    /// Private helper to push into the state locked by [Queue::wait_for_space].
+    fn push(&self, state: MutexGuard<'_, QueueState>, priority: Priority, job: Job) {
+        if self.queue.push(state, priority, job) {
+            self.spawn_worker();
         }
     }
 
-    /// Sends callable to be executed by [Worker]s.
+    /// Maximum number of queued jobs, None if unbounded.
+    pub fn get_capacity(&self) -> Option<usize> {
+        self.queue.capacity
+    }
+
+    /// Returns the counters of the pool, which stay up to date.
+    pub fn get_stats(&self) -> Arc<PoolStats> {
+        self.stats.clone()
+    }
+
+    /// Sends callable to be executed by [Worker]s, with [Priority::Normal].
+    /// If the pool is [bounded](Pool::bounded) and the queue is full,
+    /// waits until there is space, unless called from a job of this pool,
+    /// which never waits since its worker could be the one to make space.
     /// 
     /// # Parameters
     /// callable - basically any closure, since it must implemments [FnOnce]
@@ -125,22 +1195,262 @@ impl Pool {
     where 
         F: FnOnce() + Send + 'static
     {
-        let f_ptr: Job = Box::new(callable);
+        self.send(Priority::Normal, Box::new(callable));
+    }
 
-        // Option will always unwrap as long as pool is not dropped.
-        if let Err(_) = self.tx.as_ref().unwrap().send(f_ptr) {
-            eprintln!("Channel broken. Shutting down.")
+    /// Same as [execute], but workers take jobs of a higher priority first.
+    /// Jobs of the same priority run in the order they were sent.
+    /// So that a steady stream of higher priority jobs can't hold back the
+    /// others forever, every 16th job a worker takes is of the lowest priority waiting.
+    /// 
+    /// # Example
+    /// ```
+    /// use rns::worker_pool::{Pool, Priority};
+    /// 
+    /// let pool = Pool::new(4);
+    /// 
+    /// pool.execute_with_priority(Priority::Low, || println!("rebuilding the index"));
+    /// pool.execute_with_priority(Priority::High, || println!("answering a health check"));
+    /// ```
+    pub fn execute_with_priority<F>(&self, priority: Priority, callable: F)
+    where 
+        F: FnOnce() + Send + 'static
+    {
+        self.send(priority, Box::new(callable));
+    }
+
+    /// Private helper for [execute], [execute_with_priority] and [Scope::execute].
+    fn send(&self, priority: Priority, f_ptr: Job) {
+        if let Some((_, local)) = self.queue.current() {
+            if priority == Priority::Normal {
+                self.queue.push_local(&local, f_ptr);
+            } else {
+                // Other priorities are ordered in the global queue, without
+                // waiting for space, a worker waiting for its own pool could wait forever
+                self.push(self.queue.lock(), priority, f_ptr);
+            }
+            return
+        }
+        // Without a deadline, there is always space eventually
+        if let Some(state) = self.queue.wait_for_space(None) {
+            self.push(state, priority, f_ptr);
+        }
+    }
+
+    /// Same as [execute], but the value returned by callable (or its panic)
+    /// can be collected with the returned [JobHandle].
+    /// 
+    /// # Example
+    /// ```
+    /// use rns::worker_pool::Pool;
+    /// 
+    /// let pool = Pool::new(4);
+    /// 
+    /// let handles: Vec<_> = (0..4).map(|i| pool.submit(move || i * i)).collect();
+    /// let squares: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    /// 
+    /// assert!(squares == vec![0, 1, 4, 9]);
+    /// ```
+    pub fn submit<F, T>(&self, callable: F) -> JobHandle<T>
+    where 
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static
+    {
+        let (tx, rx) = mpsc::channel();
+
+        self.execute(move || {
+            match panic::catch_unwind(AssertUnwindSafe(callable)) {
+                Ok(value) => {
+                    // Nobody is waiting if the handle was dropped
+                    let _ = tx.send(Result::Ok(value));
+                }
+                Err(payload) => {
+                    let _ = tx.send(Result::Err(JobError::Panicked(panic_message(payload.as_ref()).to_string())));
+                    // The worker still counts and logs the panic
+                    panic::resume_unwind(payload);
+                }
+            }
+        });
+
+        JobHandle { rx: Some(rx) }
+    }
+
+    /// Runs [f] with a [Scope] to send jobs that borrow from the caller's stack,
+    /// and waits for all of them before returning, as [std::thread::scope] does.
+    /// Called from a job of this pool, the waiting worker runs queued jobs
+    /// meanwhile, so a scope can't starve the pool of its own workers.
+    /// 
+    /// # Panics
+    /// If [f] or any of the jobs panicked, once all jobs finished, the panic
+    /// is passed on, from [f] first, else from the first job that panicked.
+    /// 
+    /// # Example
+    /// ```
+    /// use rns::worker_pool::Pool;
+    /// 
+    /// let pool = Pool::new(4);
+    /// let numbers: Vec<u64> = (1..=1000).collect();
+    /// let mut sums = [0; 4];
+    /// 
+    /// pool.scope(|s| {
+    ///     for (chunk, sum) in numbers.chunks(250).zip(sums.iter_mut()) {
+    ///         s.execute(move || *sum = chunk.iter().sum());
+    ///     }
+    /// });
+    /// 
+    /// assert!(sums.iter().sum::<u64>() == 500500);
+    /// ```
+    pub fn scope<'env, F, T>(&self, f: F) -> T
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T
+    {
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState::default()),
+            scope: PhantomData,
+            env: PhantomData
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+
+        let current = self.queue.current();
+        let mut pending = scope.state.lock();
+        while pending.0 > 0 {
+            if let Some((id, local)) = &current {
+                drop(pending);
+                if let Some(job) = self.queue.find(local) {
+                    let job = self.queue.taken(job);
+                    // This worker is counted as busy already
+                    self.stats.busy.fetch_sub(1, Ordering::Relaxed);
+                    Worker::run(*id, job, &self.stats);
+                    pending = scope.state.lock();
+                    continue
+                }
+                // Nothing left to help with, the jobs are running elsewhere
+                pending = scope.state.lock();
+                if pending.0 == 0 {
+                    break
+                }
+            }
+            pending = scope.state.done.wait(pending).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        let panicked = pending.1.take();
+        drop(pending);
+
+        match (result, panicked) {
+            (Err(payload), _) | (Ok(_), Some(payload)) => panic::resume_unwind(payload),
+            (Ok(value), None) => value
+        }
+    }
+
+    /// Same as [execute], but fails right away if the queue is full.
+    /// Unlike [execute], a job sent from a job of the pool counts against the
+    /// capacity too, so it goes to the global queue instead of the worker's deque.
+    pub fn try_execute<F>(&self, callable: F) -> Result<(), ExecuteError<F>>
+    where 
+        F: FnOnce() + Send + 'static
+    {
+        self.push_until(callable, Instant::now()).map_err(ExecuteError::Full)
+    }
+
+    /// Same as [execute], but waits for space in the queue only up to [timeout].
+    /// Bounded from a job of the pool as well, see [try_execute].
+    pub fn execute_timeout<F>(&self, callable: F, timeout: Duration) -> Result<(), ExecuteError<F>>
+    where 
+        F: FnOnce() + Send + 'static
+    {
+        self.push_until(callable, Instant::now() + timeout).map_err(ExecuteError::TimedOut)
+    }
+
+    /// Stops accepting jobs, lets workers finish the queued ones and waits
+    /// up to [timeout] for them to stop. Workers still busy after that are
+    /// detached and counted as unstopped.
+    /// 
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// 
+    /// use rns::worker_pool::Pool;
+    /// 
+    /// let pool = Pool::new(2);
+    /// for _ in 0..4 {
+    ///     pool.execute(|| {});
+    /// }
+    /// 
+    /// let report = pool.shutdown(Duration::from_secs(5));
+    /// assert!(report.get_completed() == 4);
+    /// assert!(report.get_unstopped() == 0);
+    /// ```
+    pub fn shutdown(mut self, timeout: Duration) -> ShutdownReport {
+        self.queue.close();
+        let unstopped = self.queue.wait_stopped(Some(Instant::now() + timeout), false);
+        self.stop(0, unstopped)
+    }
+
+    /// Stops accepting jobs and drops the queued ones without running them.
+    /// Waits for idle workers to stop, workers running a job are detached
+    /// and counted as unstopped, since a job can't be interrupted.
+    /// Handles of discarded jobs from [submit] return [JobError::Lost].
+    pub fn shutdown_now(mut self) -> ShutdownReport {
+        self.queue.close();
+        let discarded = self.queue.discard();
+        let n_discarded = discarded.len();
+        // Dropped outside the lock, a job's captures may do anything on drop
+        drop(discarded);
+        let unstopped = self.queue.wait_stopped(None, true);
+        self.stop(n_discarded, unstopped)
+    }
+
+    /// Private helper to join the stopped workers, detach the others and report.
+    fn stop(&mut self, discarded: usize, unstopped: usize) -> ShutdownReport {
+        let workers = self.workers.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for w in workers.drain(..) {
+            // Retired workers are about to finish, only busy ones may not
+            if (unstopped == 0 || w.thread_h.is_finished()) && w.thread_h.join().is_err() {
+                log::error(module_path!(), "worker died", &[("worker", &w.id)]);
+            }
+        }
+
+        let report = ShutdownReport {
+            completed: self.stats.get_completed(),
+            discarded,
+            unstopped
+        };
+        log::info(
+            module_path!(),
+            "pool shut down",
+            &[("completed", &report.completed), ("discarded", &report.discarded), ("unstopped", &report.unstopped)]
+        );
+        report
+    }
+
+    /// Private helper to push a callable, handing it back if there was no space in time.
+    /// The deadline bounds the wait, so it is safe from a worker as well.
+    fn push_until<F>(&self, callable: F, deadline: Instant) -> Result<(), F>
+    where 
+        F: FnOnce() + Send + 'static
+    {
+        match self.queue.wait_for_space(Some(deadline)) {
+            Some(state) => {
+                self.push(state, Priority::Normal, Box::new(callable));
+                Result::Ok(())
+            }
+            None => Result::Err(callable)
         }
     }
 }
 
 impl Drop for Pool {
     fn drop(&mut self) {
-        // Will break channel and force workers to shutdown.
-        drop(self.tx.take());
+        // Workers finish the queued jobs and shut down.
+        // Nothing is left to join after an explicit shutdown.
+        self.queue.close();
 
-        for w in self.workers.drain(..) {
-            w.thread_h.join().unwrap();
+        let workers = self.workers.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for w in workers.drain(..) {
+            // Jobs can't take a worker down, but Drop must not panic regardless
+            if w.thread_h.join().is_err() {
+                log::error(module_path!(), "worker died", &[("worker", &w.id)]);
+            }
         }
     }
 }
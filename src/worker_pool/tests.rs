@@ -54,3 +54,479 @@ fn pool_concurrency() {
         );
     }
 }
+
+/// Waits up to a second for [condition], since workers pick up jobs asynchronously.
+fn eventually(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..100 {
+        if condition() {
+            return true;
+        }
+        thread::sleep(std::time::Duration::from_millis(10));
+    }
+    false
+}
+
+#[test]
+/// Test [PoolStats] queue depth and busy workers.
+fn pool_stats() {
+    let pool = Pool::new(1);
+    let stats = pool.get_stats();
+    assert!(stats.get_workers() == 1);
+
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || release_rx.recv().unwrap());
+    pool.execute(|| {});
+    pool.execute(|| {});
+
+    assert!(eventually(|| stats.get_busy() == 1 && stats.get_queued() == 2));
+
+    release_tx.send(()).unwrap();
+    assert!(eventually(|| stats.get_busy() == 0 && stats.get_queued() == 0));
+}
+
+#[test]
+/// Test that a panicking job leaves the worker alive and the pool droppable.
+fn pool_panic() {
+    let pool = Pool::new(1);
+    let stats = pool.get_stats();
+
+    pool.execute(|| panic!("buggy job"));
+    pool.execute(|| std::panic::panic_any(42));
+
+    let (tx, rx) = mpsc::channel();
+    pool.execute(move || tx.send("still working").unwrap());
+    assert!(rx.recv_timeout(std::time::Duration::from_secs(5)) == Ok("still working"));
+
+    assert!(stats.get_panicked() == 2);
+    assert!(eventually(|| stats.get_busy() == 0));
+    drop(pool);
+}
+
+#[test]
+#[should_panic]
+/// Test capacity restriction of a bounded pool.
+fn invalid_pool_capacity() {
+    Pool::bounded(1, 0);
+}
+
+#[test]
+/// Test that a full bounded pool refuses or delays jobs.
+fn pool_bounded() {
+    let pool = Pool::bounded(1, 1);
+    assert!(pool.get_capacity() == Some(1));
+    assert!(Pool::new(1).get_capacity().is_none());
+
+    // Occupy the worker, then fill the queue
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || release_rx.recv().unwrap());
+    assert!(eventually(|| pool.get_stats().get_busy() == 1));
+    pool.try_execute(|| {}).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let job = move || tx.send("ran").unwrap();
+    let job = match pool.try_execute(job) {
+        Err(ExecuteError::Full(job)) => job,
+        other => panic!("expected a full queue, got {other:?}")
+    };
+
+    let started = std::time::Instant::now();
+    let job = match pool.execute_timeout(job, std::time::Duration::from_millis(50)) {
+        Err(err @ ExecuteError::TimedOut(_)) => {
+            assert!(err.to_string() == "job queue stayed full until the timeout");
+            err.into_inner()
+        }
+        other => panic!("expected a timeout, got {other:?}")
+    };
+    assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+
+    // Space frees up once the worker moves on
+    release_tx.send(()).unwrap();
+    pool.execute_timeout(job, std::time::Duration::from_secs(5)).unwrap();
+    assert!(rx.recv_timeout(std::time::Duration::from_secs(5)) == Ok("ran"));
+
+    // Jobs sent from a job count against the capacity too
+    let pool = Arc::new(pool);
+    let (tx, rx) = mpsc::channel();
+    let inner = pool.clone();
+    pool.execute(move || {
+        let first = inner.try_execute(|| {}).is_ok();
+        let second = inner.try_execute(|| {}).is_ok();
+        let third = inner.execute_timeout(|| {}, std::time::Duration::from_millis(10)).is_ok();
+        tx.send((first, second, third)).unwrap();
+    });
+    assert!(rx.recv_timeout(std::time::Duration::from_secs(5)) == Ok((true, false, false)));
+    // The pool must not be dropped by its own worker
+    assert!(eventually(|| Arc::strong_count(&pool) == 1));
+}
+
+#[test]
+#[should_panic]
+/// Test min/max restriction of an elastic pool.
+fn invalid_pool_elastic() {
+    Pool::elastic(4, 2, std::time::Duration::from_secs(1));
+}
+
+#[test]
+/// Test that an elastic pool grows under load up to max and shrinks back to min.
+fn pool_elastic() {
+    let pool = Pool::elastic(1, 3, std::time::Duration::from_millis(50));
+    let stats = pool.get_stats();
+    assert!(stats.get_workers() == 1);
+
+    // Jobs that block until released keep every worker busy
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let release_rx = Arc::new(Mutex::new(release_rx));
+    for _ in 0..5 {
+        let release_rx = release_rx.clone();
+        pool.execute(move || release_rx.lock().unwrap().recv().unwrap());
+    }
+
+    assert!(eventually(|| stats.get_busy() == 3 && stats.get_queued() == 2));
+    assert!(stats.get_workers() == 3);
+
+    for _ in 0..5 {
+        release_tx.send(()).unwrap();
+    }
+    assert!(eventually(|| stats.get_busy() == 0 && stats.get_workers() == 1));
+
+    // Still serves jobs after shrinking
+    let (tx, rx) = mpsc::channel();
+    pool.execute(move || tx.send(1).unwrap());
+    assert!(rx.recv_timeout(std::time::Duration::from_secs(5)) == Ok(1));
+}
+
+#[test]
+/// Test an elastic pool that starts without workers.
+fn pool_elastic_empty() {
+    let pool = Pool::elastic(0, 2, std::time::Duration::from_millis(50));
+    let stats = pool.get_stats();
+    assert!(stats.get_workers() == 0);
+
+    let (tx, rx) = mpsc::channel();
+    pool.execute(move || tx.send(1).unwrap());
+    assert!(rx.recv_timeout(std::time::Duration::from_secs(5)) == Ok(1));
+
+    assert!(eventually(|| stats.get_workers() == 0));
+    drop(pool);
+}
+
+#[test]
+/// Test [JobHandle] join methods, including a panicking job.
+fn pool_submit() {
+    let pool = Pool::new(2);
+    let stats = pool.get_stats();
+
+    let handles: Vec<_> = (0..4).map(|i| pool.submit(move || 1 << i)).collect();
+    let result: i32 = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+    assert!(result == 15);
+
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let mut handle = pool.submit(move || {
+        release_rx.recv().unwrap();
+        "done".to_string()
+    });
+    assert!(handle.try_join().is_none());
+    assert!(handle.join_timeout(std::time::Duration::from_millis(20)).is_none());
+    release_tx.send(()).unwrap();
+    assert!(handle.join_timeout(std::time::Duration::from_secs(5)) == Some(Ok("done".to_string())));
+    // Spent
+    assert!(handle.try_join() == Some(Err(JobError::Lost)));
+
+    let handle = pool.submit(|| -> u8 { panic!("bad input") });
+    let err = handle.join().unwrap_err();
+    assert!(err == JobError::Panicked("bad input".to_string()));
+    assert!(err.to_string() == "job panicked: bad input");
+    assert!(eventually(|| stats.get_panicked() == 1));
+}
+
+#[test]
+/// Test that [Pool::shutdown] drains the queue and reports workers stuck in a job.
+fn pool_shutdown() {
+    let pool = Pool::new(2);
+    for _ in 0..8 {
+        pool.execute(|| {});
+    }
+    let report = pool.shutdown(std::time::Duration::from_secs(5));
+    assert!(report.get_completed() == 8);
+    assert!(report.get_discarded() == 0);
+    assert!(report.get_unstopped() == 0);
+
+    let pool = Pool::new(2);
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || {
+        let _ = release_rx.recv();
+    });
+    pool.execute(|| {});
+    let report = pool.shutdown(std::time::Duration::from_millis(50));
+    assert!(report.get_completed() == 1);
+    assert!(report.get_unstopped() == 1);
+    release_tx.send(()).unwrap();
+}
+
+#[test]
+/// Test that [Pool::shutdown_now] drops queued jobs without waiting for them.
+fn pool_shutdown_now() {
+    let pool = Pool::new(1);
+    let stats = pool.get_stats();
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || {
+        started_tx.send(()).unwrap();
+        let _ = release_rx.recv();
+    });
+    started_rx.recv().unwrap();
+
+    let handles: Vec<_> = (0..3).map(|i| pool.submit(move || i)).collect();
+    let report = pool.shutdown_now();
+    assert!(report.get_completed() == 0);
+    assert!(report.get_discarded() == 3);
+    assert!(report.get_unstopped() == 1);
+    assert!(stats.get_queued() == 0);
+    assert!(handles.into_iter().all(|handle| handle.join() == Err(JobError::Lost)));
+
+    release_tx.send(()).unwrap();
+    assert!(eventually(|| stats.get_workers() == 0));
+}
+
+#[test]
+/// Test that jobs sent from a job go to its worker's deque without waiting
+/// for space, and that other workers steal them while that worker is blocked.
+fn pool_stealing() {
+    let pool = Arc::new(Pool::bounded(4, 1));
+    let stats = pool.get_stats();
+    let (tx, rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+
+    let inner = pool.clone();
+    pool.execute(move || {
+        for i in 0..100 {
+            let tx = tx.clone();
+            inner.execute(move || tx.send(i).unwrap());
+        }
+        drop(inner);
+        release_rx.recv().unwrap();
+    });
+
+    let mut received: Vec<i32> = rx.iter().take(100).collect();
+    received.sort();
+    assert!(received == (0..100).collect::<Vec<_>>());
+    assert!(stats.get_queued() == 0);
+
+    release_tx.send(()).unwrap();
+    // The last reference must not be dropped by a worker, which would join itself
+    assert!(eventually(|| Arc::strong_count(&pool) == 1));
+}
+
+#[test]
+/// Test that [Pool::scope] jobs borrow from the stack and are all done when it returns.
+fn pool_scope() {
+    let pool = Pool::new(3);
+    let words = ["alpha".to_string(), "beta".to_string(), "gamma".to_string()];
+    let mut lengths = vec![0; words.len()];
+
+    let returned = pool.scope(|s| {
+        for (word, length) in words.iter().zip(lengths.iter_mut()) {
+            s.execute(move || *length = word.len());
+        }
+        "returned"
+    });
+    assert!(returned == "returned");
+    assert!(lengths == vec![5, 4, 5]);
+}
+
+#[test]
+/// Test that a scope inside a job of a single worker pool runs its jobs
+/// on the waiting worker instead of waiting for itself.
+fn pool_scope_nested() {
+    let pool = Pool::new(1);
+    let counter = AtomicUsize::new(0);
+
+    pool.scope(|s| {
+        s.execute(|| {
+            pool.scope(|inner| {
+                for _ in 0..10 {
+                    inner.execute(|| {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                    });
+                }
+            });
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+    });
+    assert!(counter.load(Ordering::Relaxed) == 11);
+    assert!(pool.get_stats().get_busy() <= 1);
+}
+
+#[test]
+/// Test that a panicking scoped job is passed on after the other jobs finished.
+fn pool_scope_panic() {
+    let pool = Pool::new(2);
+    let finished = AtomicUsize::new(0);
+
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        pool.scope(|s| {
+            s.execute(|| panic!("scoped failure"));
+            for _ in 0..4 {
+                s.execute(|| {
+                    thread::sleep(std::time::Duration::from_millis(10));
+                    finished.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+        });
+    }));
+    let payload = result.unwrap_err();
+    assert!(panic_message(payload.as_ref()) == "scoped failure");
+    assert!(finished.load(Ordering::Relaxed) == 4);
+    assert!(pool.get_stats().get_panicked() == 1);
+
+    // The workers survived
+    pool.scope(|s| s.execute(|| { finished.fetch_add(1, Ordering::Relaxed); }));
+    assert!(finished.load(Ordering::Relaxed) == 5);
+}
+
+/// Sends a job that holds the only worker of [pool] until the returned sender is used.
+fn hold_worker(pool: &Pool) -> Sender<()> {
+    let (started_tx, started_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    pool.execute(move || {
+        started_tx.send(()).unwrap();
+        release_rx.recv().unwrap();
+    });
+    started_rx.recv().unwrap();
+    release_tx
+}
+
+#[test]
+/// Test that jobs run by [Priority], and in order within one.
+fn pool_priority() {
+    let pool = Pool::new(1);
+    let release = hold_worker(&pool);
+
+    let (tx, rx) = mpsc::channel();
+    let jobs = [
+        (Priority::Low, "low 1"),
+        (Priority::Normal, "normal 1"),
+        (Priority::High, "high 1"),
+        (Priority::Low, "low 2"),
+        (Priority::High, "high 2"),
+        (Priority::Normal, "normal 2")
+    ];
+    for (priority, name) in jobs {
+        let tx = tx.clone();
+        pool.execute_with_priority(priority, move || tx.send(name).unwrap());
+    }
+    drop(tx);
+    release.send(()).unwrap();
+
+    let order: Vec<_> = rx.iter().collect();
+    assert!(order == vec!["high 1", "high 2", "normal 1", "normal 2", "low 1", "low 2"]);
+}
+
+#[test]
+/// Test that a low priority job runs under a sustained stream of high priority ones.
+fn pool_priority_starvation() {
+    let pool = Pool::new(1);
+    let release = hold_worker(&pool);
+
+    let (tx, rx) = mpsc::channel();
+    let low_tx = tx.clone();
+    pool.execute_with_priority(Priority::Low, move || low_tx.send("low").unwrap());
+    for _ in 0..100 {
+        let tx = tx.clone();
+        pool.execute_with_priority(Priority::High, move || tx.send("high").unwrap());
+    }
+    drop(tx);
+    release.send(()).unwrap();
+
+    let order: Vec<_> = rx.iter().collect();
+    let low = order.iter().position(|name| *name == "low").unwrap();
+    assert!(low < 16, "low priority job ran after {low} others");
+}
+
+#[test]
+/// Test that an idle worker steals jobs of [Priority::Normal] from another
+/// worker's deque before it runs a queued job of [Priority::Low].
+fn pool_priority_stealing() {
+    let pool = Arc::new(Pool::new(2));
+    let release = hold_worker(&pool);
+
+    let (tx, rx) = mpsc::channel();
+    let (queued_tx, queued_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let inner = pool.clone();
+    pool.execute(move || {
+        for _ in 0..4 {
+            let tx = tx.clone();
+            inner.execute(move || tx.send("normal").unwrap());
+        }
+        inner.execute_with_priority(Priority::Low, move || tx.send("low").unwrap());
+        drop(inner);
+        queued_tx.send(()).unwrap();
+        release_rx.recv().unwrap();
+    });
+
+    // Both jobs wait until the other worker is free to take one
+    queued_rx.recv().unwrap();
+    release.send(()).unwrap();
+
+    let order: Vec<_> = rx.iter().collect();
+    release_tx.send(()).unwrap();
+    assert!(order == ["normal", "normal", "normal", "normal", "low"], "ran in order {order:?}");
+}
+
+#[test]
+/// Test that [PoolBuilder] reports invalid settings instead of panicking.
+fn builder_errors() {
+    assert!(matches!(Pool::builder().workers(0).build(), Err(PoolError::WorkerCount(0))));
+    assert!(matches!(Pool::builder().workers(256).build(), Err(PoolError::WorkerCount(256))));
+    assert!(matches!(
+        Pool::builder().elastic(3, 2, std::time::Duration::from_secs(1)).build(),
+        Err(PoolError::MinAboveMax { min: 3, max: 2 })
+    ));
+    assert!(matches!(Pool::builder().workers(1).capacity(0).build(), Err(PoolError::ZeroCapacity)));
+
+    let err = Pool::builder().workers(0).build().err().unwrap();
+    assert!(err.to_string() == "0 workers is outside of the range [1, 256)");
+    assert!(Pool::builder().build().is_ok());
+}
+
+#[test]
+/// Test thread names and that start and stop hooks run once per worker.
+fn builder_threads() {
+    let started = Arc::new(Mutex::new(Vec::new()));
+    let stopped = Arc::new(AtomicUsize::new(0));
+
+    let started_hook = started.clone();
+    let stopped_hook = stopped.clone();
+    let pool = Pool::builder()
+        .workers(2)
+        .thread_name("test-pool")
+        .stack_size(256 * 1024)
+        .on_thread_start(move |id| started_hook.lock().unwrap().push(id))
+        .on_thread_stop(move |_| {
+            stopped_hook.fetch_add(1, Ordering::Relaxed);
+        })
+        .build()
+        .unwrap();
+
+    let name = pool.submit(|| thread::current().name().map(String::from)).join().unwrap();
+    assert!(name.is_some_and(|name| name == "test-pool-0" || name == "test-pool-1"));
+
+    drop(pool);
+    let mut started = started.lock().unwrap().clone();
+    started.sort();
+    assert!(started == vec![0, 1]);
+    assert!(stopped.load(Ordering::Relaxed) == 2);
+}
+
+#[test]
+/// Test that a panicking start hook does not take the worker down.
+fn builder_hook_panic() {
+    let pool = Pool::builder()
+        .workers(1)
+        .on_thread_start(|_| panic!("hook failure"))
+        .build()
+        .unwrap();
+
+    assert!(pool.submit(|| 42).join() == Ok(42));
+}